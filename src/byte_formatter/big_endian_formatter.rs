@@ -0,0 +1,19 @@
+use crate::byte_formatter::byte_formatter::ByteFormatter;
+use crate::byte_formatter::endianness::Endianness;
+
+#[derive(Default)]
+pub struct BigEndianFormatter {}
+
+impl ByteFormatter for BigEndianFormatter {
+    fn unwrap_bytes(&self, bytes: u16) -> [u8; 2] {
+        [(bytes >> 8) as u8, (bytes & (0xff)) as u8]
+    }
+
+    fn wrap_bytes(&self, bytes: [u8; 2]) -> u16 {
+        ((bytes[0] as u16) << 8) + (bytes[1] as u16)
+    }
+
+    fn endianness(&self) -> Endianness {
+        Endianness::Big
+    }
+}
\ No newline at end of file
@@ -1,5 +1,9 @@
+use crate::byte_formatter::endianness::Endianness;
+
 pub trait ByteFormatter {
     fn unwrap_bytes(&self, bytes: u16) -> [u8; 2];
 
     fn wrap_bytes(&self, bytes: [u8; 2]) -> u16;
+
+    fn endianness(&self) -> Endianness;
 }
\ No newline at end of file
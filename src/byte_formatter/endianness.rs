@@ -0,0 +1,24 @@
+use crate::byte_formatter::big_endian_formatter::BigEndianFormatter;
+use crate::byte_formatter::byte_formatter::ByteFormatter;
+use crate::byte_formatter::little_endian_formatter::LittleEndianFormatter;
+use std::rc::Rc;
+use strum_macros::{Display, EnumString, FromRepr};
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, EnumString, Display, FromRepr)]
+#[strum(ascii_case_insensitive, serialize_all = "lowercase")]
+#[repr(u8)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big
+}
+
+impl Endianness {
+
+    pub fn formatter(&self) -> Rc<dyn ByteFormatter> {
+        match self {
+            Endianness::Little => Rc::new(LittleEndianFormatter::default()),
+            Endianness::Big => Rc::new(BigEndianFormatter::default())
+        }
+    }
+}
\ No newline at end of file
@@ -1,4 +1,5 @@
 use crate::byte_formatter::byte_formatter::ByteFormatter;
+use crate::byte_formatter::endianness::Endianness;
 
 #[derive(Default)]
 pub struct LittleEndianFormatter {}
@@ -11,4 +12,8 @@ impl ByteFormatter for LittleEndianFormatter {
     fn wrap_bytes(&self, bytes: [u8; 2]) -> u16 {
         ((bytes[1] as u16) << 8) + (bytes[0] as u16)
     }
+
+    fn endianness(&self) -> Endianness {
+        Endianness::Little
+    }
 }
\ No newline at end of file
@@ -1,2 +1,4 @@
 pub mod byte_formatter;
-pub mod little_endian_formatter;
\ No newline at end of file
+pub mod little_endian_formatter;
+pub mod big_endian_formatter;
+pub mod endianness;
\ No newline at end of file
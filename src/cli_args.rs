@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub struct CliArgs {
+    pub positional: Vec<String>,
+    options: HashMap<String, Vec<String>>
+}
+
+impl CliArgs {
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<CliArgs, String> {
+        let mut positional = Vec::new();
+        let mut options: HashMap<String, Vec<String>> = HashMap::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = args.next().ok_or(format!("missing value for --{name}"))?;
+                options.entry(name.to_string()).or_default().push(value);
+            } else {
+                positional.push(arg);
+            }
+        }
+
+        Ok(CliArgs {
+            positional,
+            options
+        })
+    }
+
+    // опечатка в имени параметра иначе молча игнорировалась бы
+    pub fn check_options(&self, command: &str, allowed: &[&str]) -> Result<(), String> {
+        let mut unknown = self.options.keys().filter(|v| !allowed.contains(&v.as_str())).collect::<Vec<_>>();
+        unknown.sort();
+        match unknown.first() {
+            Some(name) => Err(format!("unknown option --{name} for `{command}`")),
+            None => Ok(())
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.options.get(name).and_then(|v| v.last()).map(|v| v.as_str())
+    }
+
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.options.get(name).map(|v| v.iter().map(|v| v.as_str()).collect()).unwrap_or_default()
+    }
+
+    pub fn get_parsed<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.get(name)
+            .map(|v| v.parse::<T>().map_err(|_| format!("invalid value for --{name}: {v}")))
+            .transpose()
+    }
+}
\ No newline at end of file
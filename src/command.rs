@@ -22,7 +22,32 @@ pub enum CommandType {
     LRG,
     JMPG,
     JMPNG,
-    MULT
+    MULT,
+    MOVH,
+    PUSH,
+    POP,
+    CALL,
+    RET,
+    AND,
+    OR,
+    XOR,
+    NOT,
+    SHL,
+    SHR,
+    ROL,
+    ROR,
+    DIV,
+    MOD,
+    DIVU,
+    MODU,
+    JZ,
+    JNZ,
+    JL,
+    JLE,
+    JGE,
+    JC,
+    JO,
+    HALT
 }
 
 impl CommandType {
@@ -40,8 +65,37 @@ impl CommandType {
             CommandType::LRG => 8,
             CommandType::JMPG => 9,
             CommandType::JMPNG => 10,
-            CommandType::MULT => 11
+            CommandType::MULT => 11,
+            CommandType::MOVH => 12,
+            CommandType::PUSH => 13,
+            CommandType::POP => 14,
+            CommandType::CALL => 15,
+            CommandType::RET => 16,
+            CommandType::AND => 17,
+            CommandType::OR => 18,
+            CommandType::XOR => 19,
+            CommandType::NOT => 20,
+            CommandType::SHL => 21,
+            CommandType::SHR => 22,
+            CommandType::ROL => 23,
+            CommandType::ROR => 24,
+            CommandType::DIV => 25,
+            CommandType::MOD => 26,
+            CommandType::DIVU => 27,
+            CommandType::MODU => 28,
+            CommandType::JZ => 29,
+            CommandType::JNZ => 30,
+            CommandType::JL => 31,
+            CommandType::JLE => 32,
+            CommandType::JGE => 33,
+            CommandType::JC => 34,
+            CommandType::JO => 35,
+            CommandType::HALT => 36
         }
     }
 
+    pub const fn requires_operand(&self) -> bool {
+        !matches!(self, CommandType::NOP | CommandType::RET | CommandType::NOT | CommandType::HALT)
+    }
+
 }
\ No newline at end of file
@@ -1 +1,2 @@
-pub mod default_error;
\ No newline at end of file
+pub mod default_error;
+pub mod byte_reader;
\ No newline at end of file
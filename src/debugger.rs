@@ -0,0 +1,136 @@
+use crate::history::History;
+use crate::snapshot::{load_snapshot, save_snapshot};
+use crate::vm::{StepOutcome, VmConfigOverrides, VmState};
+use std::collections::BTreeSet;
+use std::fs;
+
+pub struct Debugger {
+    pub vm: VmState,
+    pub breakpoints: BTreeSet<u16>,
+    pub cursor: u16,
+    pub message: Option<String>,
+    pub history: History,
+    pub snapshot_path: String,
+    // `--cmp` и `--overflow` применяются и к снимкам, загруженным из отладчика
+    pub config_overrides: VmConfigOverrides
+}
+
+impl Debugger {
+
+    pub fn new(vm: VmState, breakpoints: BTreeSet<u16>, history_size: usize, snapshot_path: String, config_overrides: VmConfigOverrides) -> Debugger {
+        let cursor = vm.pc.pc;
+        Debugger {
+            vm,
+            breakpoints,
+            cursor,
+            message: None,
+            history: History::new(history_size),
+            snapshot_path,
+            config_overrides
+        }
+    }
+
+    pub fn step(&mut self) {
+        if self.vm.halted {
+            return;
+        }
+
+        self.message = match self.history.step(&mut self.vm) {
+            Ok(StepOutcome::Executed) => None,
+            Ok(StepOutcome::Halted) => Some("program halted".to_string()),
+            Ok(StepOutcome::RanOffEnd) => Some("program ran off the end of loaded code".to_string()),
+            Err(fault) => Some(fault.to_string())
+        };
+        self.cursor = self.vm.pc.pc;
+    }
+
+    pub fn continue_run(&mut self, max_steps: u64) {
+        if self.vm.halted {
+            return;
+        }
+
+        self.message = Some(format!("step budget of {max_steps} exhausted"));
+        for _ in 0..max_steps {
+            match self.history.step(&mut self.vm) {
+                Ok(StepOutcome::Executed) => {}
+                Ok(StepOutcome::Halted) => {
+                    self.message = Some("program halted".to_string());
+                    break;
+                }
+                Ok(StepOutcome::RanOffEnd) => {
+                    self.message = Some("program ran off the end of loaded code".to_string());
+                    break;
+                }
+                Err(fault) => {
+                    self.message = Some(fault.to_string());
+                    break;
+                }
+            }
+            if self.breakpoints.contains(&self.vm.pc.pc) {
+                self.message = Some(format!("breakpoint at {:04X}", self.vm.pc.pc));
+                break;
+            }
+        }
+        self.cursor = self.vm.pc.pc;
+    }
+
+    pub fn step_back(&mut self) {
+        self.message = if self.history.step_back(&mut self.vm) {
+            Some(format!("stepped back, {} step(s) left in history", self.history.depth()))
+        } else {
+            Some("no steps in history".to_string())
+        };
+        self.cursor = self.vm.pc.pc;
+    }
+
+    pub fn step_forward(&mut self) {
+        if self.history.step_forward(&mut self.vm) {
+            self.message = Some("replayed step from history".to_string());
+            self.cursor = self.vm.pc.pc;
+        } else {
+            self.step();
+        }
+    }
+
+    pub fn save_snapshot(&mut self) {
+        self.message = match fs::write(&self.snapshot_path, save_snapshot(&self.vm)) {
+            Ok(()) => Some(format!("snapshot saved to {}", self.snapshot_path)),
+            Err(e) => Some(format!("could not save snapshot: {e}"))
+        };
+    }
+
+    pub fn load_snapshot(&mut self) {
+        let loaded = fs::read(&self.snapshot_path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| load_snapshot(&bytes));
+
+        self.message = match loaded {
+            Ok(mut vm) => {
+                // история относится к прежнему состоянию и после загрузки смысла не имеет
+                vm.config = self.config_overrides.apply(vm.config);
+                self.vm = vm;
+                self.history.clear();
+                self.cursor = self.vm.pc.pc;
+                Some(format!("snapshot loaded from {}", self.snapshot_path))
+            }
+            Err(e) => Some(format!("could not load snapshot: {e}"))
+        };
+    }
+
+    pub fn toggle_breakpoint(&mut self) {
+        if !self.breakpoints.remove(&self.cursor) {
+            self.breakpoints.insert(self.cursor);
+        }
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        let mut addresses = self.vm.command_memory.get_all().into_iter().map(|v| v.0).collect::<Vec<_>>();
+        addresses.sort();
+
+        let current = addresses.iter().position(|v| *v == self.cursor).unwrap_or(0);
+        let next = current.saturating_add_signed(delta).min(addresses.len().saturating_sub(1));
+        if let Some(address) = addresses.get(next) {
+            self.cursor = *address;
+        }
+    }
+}
\ No newline at end of file
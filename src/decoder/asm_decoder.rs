@@ -1,41 +1,531 @@
+use crate::byte_formatter::endianness::Endianness;
 use crate::command::{Command, CommandType};
-use crate::common::default_error::DefaultError;
+use crate::decoder::asm_diagnostic::Diagnostic;
+use crate::decoder::asm_expr::eval_expr;
+use crate::decoder::asm_include::include_files;
+use crate::decoder::asm_lexer::{is_valid_label, parse_args, span, tokenize, Token};
+use crate::decoder::asm_macro::expand_macros;
+use crate::decoder::asm_source::{LineRole, SourceLine};
 use crate::operand::Operand;
 use crate::register::RegisterName;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::str::FromStr;
+use strum_macros::EnumString;
 
-pub fn parse_asm(lines: &[String]) -> Result<Vec<Command>, String> {
-    let mut res = Vec::new();
-    for line in lines {
-        let comment_index = line.find("//");
+#[derive(Copy, Clone)]
+enum Mnemonic {
+    Command(CommandType),
+    // псевдокоманда загрузки 16-битной константы: mov младший байт, movh старший байт
+    MovWord,
+}
 
-        let filtered_line = if let Some(index) = comment_index {
-            &line[..index]
+impl Mnemonic {
+
+    fn parse(text: &str) -> Option<Mnemonic> {
+        if text.eq_ignore_ascii_case("movw") {
+            Some(Mnemonic::MovWord)
         } else {
-            &line
+            CommandType::from_str(text).ok().map(Mnemonic::Command)
+        }
+    }
+
+    fn size(&self) -> u16 {
+        match self {
+            Mnemonic::Command(_) => 2,
+            Mnemonic::MovWord => 4
+        }
+    }
+
+    fn requires_operand(&self) -> bool {
+        match self {
+            Mnemonic::Command(command_type) => command_type.requires_operand(),
+            Mnemonic::MovWord => true
+        }
+    }
+}
+
+// директивы записываются с точкой: `.word 1, 2`; `.equ`/`.const` задают именованную константу,
+// `.entry` — точку входа, `.endian` — порядок байт программы
+#[derive(Debug, Copy, Clone, Eq, PartialEq, EnumString)]
+#[strum(ascii_case_insensitive, serialize_all = "lowercase")]
+enum Directive {
+    Org,
+    Word,
+    Byte,
+    String,
+    Fill,
+    Equ,
+    Const,
+    Entry,
+    Endian,
+}
+
+enum OperandValue {
+    Register(RegisterName),
+    Number(i64),
+}
+
+// отрицательные значения записываются в дополнительном коде
+const BYTE_RANGE: RangeInclusive<i64> = -0x80..=0xff;
+const WORD_RANGE: RangeInclusive<i64> = -0x8000..=0xffff;
+const ADDRESS_RANGE: RangeInclusive<i64> = 0..=0xffff;
+const DATA_MEMORY_SIZE: u32 = 1 << 16;
+
+enum Item<'a> {
+    Instruction {
+        address: u16,
+        mnemonic: Mnemonic,
+        operand: Option<Token<'a>>,
+    },
+    Data {
+        directive: Directive,
+        directive_token: Token<'a>,
+        args: Vec<Token<'a>>,
+        address: u16,
+        // содержимое `.string` и размер `.fill` известны уже на первом проходе
+        bytes: Vec<u8>,
+    },
+}
+
+struct AsmLine<'a> {
+    // номер строки в раскрытом тексте, для листинга
+    index: usize,
+    source: &'a SourceLine,
+    item: Item<'a>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SymbolKind {
+    CodeLabel,
+    DataLabel,
+    Constant,
+}
+
+impl SymbolKind {
+
+    fn name(&self) -> &'static str {
+        match self {
+            SymbolKind::CodeLabel | SymbolKind::DataLabel => "label",
+            SymbolKind::Constant => "constant"
+        }
+    }
+}
+
+// метка или константа; имена меток и констант не должны совпадать
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub value: i64,
+    pub line_number: usize,
+}
+
+type SymbolError<'a> = (Token<'a>, String);
+
+// результат трансляции строки: команды по адресу в памяти команд или байты в памяти данных
+pub enum ListingContent {
+    Empty,
+    Commands(u16, Vec<Command>),
+    Data(u16, Vec<u8>),
+}
+
+// строка после включения файлов и раскрытия макросов, для листинга
+pub struct ListingLine {
+    pub source: SourceLine,
+    pub content: ListingContent,
+}
+
+pub struct AsmProgram {
+    pub endianness: Endianness,
+    pub entry: Option<u16>,
+    pub commands: Vec<Command>,
+    pub data: BTreeMap<u16, u8>,
+    pub listing: Vec<ListingLine>,
+    pub symbols: BTreeMap<String, Symbol>,
+}
+
+// `endianness` — порядок байт, явно запрошенный при компиляции; он должен совпадать с директивой `.endian`
+pub fn parse_asm(lines: Vec<SourceLine>, include_paths: &[PathBuf], endianness: Option<Endianness>) -> Result<AsmProgram, Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    let included = include_files(lines, include_paths, &mut diagnostics);
+    let expanded = expand_macros(included, &mut diagnostics);
+
+    let mut symbols: HashMap<&str, Symbol> = HashMap::new();
+    let mut pending_labels: Vec<(Token, &SourceLine)> = Vec::new();
+    let mut asm_lines = Vec::new();
+    let mut address = 0u16;
+    let mut data_address = 0u32;
+    let mut endian_directive: Option<(Endianness, Token, &SourceLine)> = None;
+    let mut entry_directive: Option<(Token, &SourceLine)> = None;
+
+    // первый проход: адреса меток и значения констант; метка относится к следующей за ней команде или директиве данных
+    for (index, source) in expanded.iter().enumerate() {
+        let line = source.text.as_str();
+        let error = |token: Token, message: String| source.diagnostic(token.offset, token.text.len(), message);
+
+        if source.role == LineRole::ListingOnly {
+            continue;
+        }
+        let (line_labels, mut tokens) = tokenize(line);
+        pending_labels.extend(line_labels.into_iter().map(|label| (label, source)));
+        if source.role == LineRole::LabelsOnly {
+            continue;
+        }
+
+        let Some(mnemonic) = tokens.next() else {
+            continue;
         };
 
-        if filtered_line.is_empty() {
+        if let Some(name) = mnemonic.text.strip_prefix('.') {
+            let Ok(directive) = Directive::from_str(name) else {
+                diagnostics.push(error(mnemonic, format!("unknown directive `{}`", mnemonic.text)));
+                continue;
+            };
+            let args = match parse_args(line, tokens) {
+                Ok(args) => args,
+                Err((token, message)) => {
+                    diagnostics.push(error(token, message));
+                    continue;
+                }
+            };
+
+            let (min_args, max_args) = match directive {
+                Directive::Org | Directive::String | Directive::Entry | Directive::Endian => (1, 1),
+                Directive::Fill => (1, 2),
+                Directive::Equ | Directive::Const => (2, 2),
+                Directive::Word | Directive::Byte => (1, usize::MAX)
+            };
+            if args.len() < min_args {
+                diagnostics.push(error(mnemonic, format!("missing operand for `{}`", mnemonic.text)));
+                continue;
+            }
+            if args.len() > max_args {
+                diagnostics.push(error(args[max_args], format!("unexpected token `{}`", args[max_args].text)));
+                continue;
+            }
+
+            let bytes = match directive {
+                Directive::Org => {
+                    match evaluate_defined(args[0], &symbols).and_then(|value| check_range(args[0], value, ADDRESS_RANGE)) {
+                        Ok(value) => data_address = value as u32,
+                        Err((token, message)) => diagnostics.push(error(token, message))
+                    }
+                    continue;
+                }
+                Directive::Entry => {
+                    // значение вычисляется после первого прохода, поэтому точкой входа может быть метка ниже
+                    match entry_directive {
+                        Some((_, defined)) => diagnostics.push(error(mnemonic, format!("duplicate `.entry` (first defined at line {})", defined.line_number))),
+                        None => entry_directive = Some((args[0], source))
+                    }
+                    continue;
+                }
+                Directive::Endian => {
+                    match (Endianness::from_str(args[0].text), endian_directive) {
+                        (Err(_), _) => diagnostics.push(error(args[0], format!("expected `little` or `big`, found `{}`", args[0].text))),
+                        (Ok(_), Some((_, _, defined))) => diagnostics.push(error(mnemonic, format!("duplicate `.endian` (first defined at line {})", defined.line_number))),
+                        (Ok(value), None) => endian_directive = Some((value, args[0], source))
+                    }
+                    continue;
+                }
+                Directive::Equ | Directive::Const => {
+                    match evaluate_defined(args[1], &symbols) {
+                        Ok(value) => define_symbol(&mut symbols, &mut diagnostics, SymbolKind::Constant, args[0], source, value),
+                        Err((token, message)) => diagnostics.push(error(token, message))
+                    }
+                    continue;
+                }
+                Directive::String => match parse_string(args[0].text) {
+                    Ok(bytes) => bytes,
+                    Err(message) => {
+                        diagnostics.push(error(args[0], message));
+                        continue;
+                    }
+                },
+                Directive::Fill => match evaluate_defined(args[0], &symbols).and_then(|value| check_range(args[0], value, 0..=DATA_MEMORY_SIZE as i64)) {
+                    Ok(count) => vec![0; count as usize],
+                    Err((token, message)) => {
+                        diagnostics.push(error(token, message));
+                        continue;
+                    }
+                },
+                Directive::Word => vec![0; args.len() * 2],
+                Directive::Byte => vec![0; args.len()]
+            };
+
+            for (label, label_source) in pending_labels.drain(..) {
+                define_symbol(&mut symbols, &mut diagnostics, SymbolKind::DataLabel, label, label_source, data_address as i64);
+            }
+            if data_address + bytes.len() as u32 > DATA_MEMORY_SIZE {
+                diagnostics.push(error(mnemonic, "data does not fit in the 64 KiB data memory".to_string()));
+                continue;
+            }
+
+            let size = bytes.len() as u32;
+            asm_lines.push(AsmLine {
+                index,
+                source,
+                item: Item::Data {
+                    directive,
+                    directive_token: mnemonic,
+                    args,
+                    address: data_address as u16,
+                    bytes,
+                },
+            });
+            data_address += size;
             continue;
         }
 
-        let command = filtered_line.split(" ").collect::<Vec<&str>>();
-        let command_type = CommandType::from_str(command[0]).default_res()?;
-        let operand = parse_operand(command[1])?;
+        // операнд — всё оставшееся выражение
+        let operand = span(line, &tokens.collect::<Vec<_>>());
 
-        res.push(Command {
-            command_type,
-            operand,
+        let Some(parsed_mnemonic) = Mnemonic::parse(mnemonic.text) else {
+            diagnostics.push(error(mnemonic, format!("unknown mnemonic `{}`", mnemonic.text)));
+            continue;
+        };
+        if operand.is_none() && parsed_mnemonic.requires_operand() {
+            diagnostics.push(error(mnemonic, format!("missing operand for `{}`", mnemonic.text)));
+            continue;
+        }
+
+        for (label, label_source) in pending_labels.drain(..) {
+            define_symbol(&mut symbols, &mut diagnostics, SymbolKind::CodeLabel, label, label_source, address as i64);
+        }
+        asm_lines.push(AsmLine {
+            index,
+            source,
+            item: Item::Instruction {
+                address,
+                mnemonic: parsed_mnemonic,
+                operand,
+            },
+        });
+        address = address.wrapping_add(parsed_mnemonic.size());
+    }
+
+    // метки в конце файла указывают на конец кода
+    for (label, label_source) in pending_labels.drain(..) {
+        define_symbol(&mut symbols, &mut diagnostics, SymbolKind::CodeLabel, label, label_source, address as i64);
+    }
+
+    let endianness = match (endian_directive, endianness) {
+        (Some((declared, token, source)), Some(requested)) if declared != requested => {
+            diagnostics.push(source.diagnostic(token.offset, token.text.len(), format!("program is {declared}-endian, but `--endian {requested}` was requested")));
+            requested
+        }
+        (declared, requested) => declared.map(|v| v.0).or(requested).unwrap_or_default()
+    };
+    let bytes_formatter = endianness.formatter();
+
+    let entry = entry_directive.and_then(|(token, source)| {
+        match evaluate(token, &symbols).and_then(|value| check_range(token, value, ADDRESS_RANGE)) {
+            Ok(value) => Some(value as u16),
+            Err((token, message)) => {
+                diagnostics.push(source.diagnostic(token.offset, token.text.len(), message));
+                None
+            }
+        }
+    });
+
+    // второй проход: разрешение операндов и значений данных
+    let mut commands = Vec::new();
+    let mut data = BTreeMap::new();
+    let mut listing = expanded.iter()
+        .map(|source| ListingLine {
+            source: source.clone(),
+            content: ListingContent::Empty
         })
+        .collect::<Vec<_>>();
+    for asm_line in asm_lines {
+        let error = |token: Token, message: String| asm_line.source.diagnostic(token.offset, token.text.len(), message);
+
+        match asm_line.item {
+            Item::Instruction { address, mnemonic, operand } => {
+                let value = match operand {
+                    None => Ok(OperandValue::Number(0)),
+                    Some(token) => parse_operand(token, &symbols)
+                };
+
+                match value.and_then(|value| encode_mnemonic(mnemonic, value).map_err(|message| (operand.unwrap(), message))) {
+                    Ok(encoded) => {
+                        commands.extend(encoded.iter().cloned());
+                        listing[asm_line.index].content = ListingContent::Commands(address, encoded);
+                    }
+                    Err((token, message)) => diagnostics.push(error(token, message))
+                }
+            }
+            Item::Data { directive, directive_token, args, address, mut bytes } => {
+                let resolved = match directive {
+                    Directive::Word => args.iter()
+                        .map(|arg| evaluate(*arg, &symbols)
+                            .and_then(|value| check_range(*arg, value, WORD_RANGE))
+                            .map(|value| bytes_formatter.unwrap_bytes(value as u16)))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map(|words| words.concat()),
+                    Directive::Byte => args.iter()
+                        .map(|arg| evaluate(*arg, &symbols)
+                            .and_then(|value| check_range(*arg, value, BYTE_RANGE))
+                            .map(|value| value as u8))
+                        .collect(),
+                    Directive::Fill => match args.get(1) {
+                        Some(arg) => evaluate(*arg, &symbols)
+                            .and_then(|value| check_range(*arg, value, BYTE_RANGE))
+                            .map(|value| vec![value as u8; bytes.len()]),
+                        None => Ok(bytes)
+                    },
+                    Directive::String | Directive::Org | Directive::Equ | Directive::Const | Directive::Entry | Directive::Endian => Ok(bytes)
+                };
+
+                match resolved {
+                    Ok(resolved) => bytes = resolved,
+                    Err((token, message)) => {
+                        diagnostics.push(error(token, message));
+                        continue;
+                    }
+                }
+
+                for (offset, byte) in bytes.iter().enumerate() {
+                    let byte_address = address + offset as u16;
+                    if data.insert(byte_address, *byte).is_some() {
+                        diagnostics.push(error(directive_token, format!("data overlaps previously defined data at {byte_address:04X}")));
+                        break;
+                    }
+                }
+                listing[asm_line.index].content = ListingContent::Data(address, bytes);
+            }
+        }
+    }
+
+    if diagnostics.is_empty() {
+        let symbols = symbols.into_iter()
+            .map(|(name, symbol)| (name.to_string(), symbol))
+            .collect();
+        Ok(AsmProgram { endianness, entry, commands, data, listing, symbols })
+    } else {
+        diagnostics.sort_by_key(|v| v.position());
+        Err(diagnostics)
     }
+}
+
+fn define_symbol<'a>(symbols: &mut HashMap<&'a str, Symbol>, diagnostics: &mut Vec<Diagnostic>, kind: SymbolKind, name: Token<'a>, source: &SourceLine, value: i64) {
+    let name_kind = kind.name();
+    let error = |message: String| source.diagnostic(name.offset, name.text.len(), message);
+
+    if !is_valid_label(name.text) {
+        diagnostics.push(error(format!("invalid {name_kind} name `{}`", name.text)));
+    } else if RegisterName::from_str(name.text).is_ok() {
+        diagnostics.push(error(format!("{name_kind} `{}` conflicts with register name", name.text)));
+    } else if let Some(defined) = symbols.get(name.text) {
+        diagnostics.push(error(format!("duplicate {name_kind} `{}` (first defined at line {})", name.text, defined.line_number)));
+    } else {
+        symbols.insert(name.text, Symbol {
+            kind,
+            value,
+            line_number: source.line_number
+        });
+    }
+}
+
+fn parse_string(text: &str) -> Result<Vec<u8>, String> {
+    let Some(body) = text.strip_prefix('"').and_then(|v| v.strip_suffix('"')).filter(|_| text.len() >= 2) else {
+        return Err(if text.starts_with('"') { "unterminated string".to_string() } else { format!("expected string, found `{text}`") });
+    };
+
+    let mut res = Vec::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        let c = if c == '\\' {
+            match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some('\\') => '\\',
+                Some('"') => '"',
+                other => return Err(format!("unknown escape sequence `\\{}`", other.map(String::from).unwrap_or_default()))
+            }
+        } else {
+            c
+        };
+        if !c.is_ascii() {
+            return Err(format!("non-ASCII character `{c}` in string"));
+        }
+        res.push(c as u8);
+    }
+    // строка завершается нулевым байтом
+    res.push(0);
 
     Ok(res)
 }
 
-fn parse_operand(data: &str) -> Result<Operand, String> {
-    if let Ok(number) = u16::from_str(data) {
-        Ok(Operand::Literal(number))
+fn check_range(token: Token, value: i64, range: RangeInclusive<i64>) -> Result<i64, SymbolError> {
+    if range.contains(&value) {
+        Ok(value)
     } else {
-        Ok(Operand::Register(RegisterName::from_str(data).default_res()?))
+        Err((token, format!("value {value} is out of range ({}..={})", range.start(), range.end())))
+    }
+}
+
+fn encode_mnemonic(mnemonic: Mnemonic, value: OperandValue) -> Result<Vec<Command>, String> {
+    match (mnemonic, value) {
+        (Mnemonic::Command(command_type), OperandValue::Register(register)) => Ok(vec![Command {
+            command_type,
+            operand: Operand::Register(register),
+        }]),
+        (Mnemonic::Command(command_type), OperandValue::Number(number)) => {
+            if !BYTE_RANGE.contains(&number) {
+                return Err(format!("value {number} does not fit in 8-bit operand field ({}..={}), use `movw` to load 16-bit constants", BYTE_RANGE.start(), BYTE_RANGE.end()));
+            }
+            let operand = if number < 0 {
+                Operand::SignedLiteral(number as i8)
+            } else {
+                Operand::Literal(number as u16)
+            };
+            Ok(vec![Command {
+                command_type,
+                operand,
+            }])
+        }
+        (Mnemonic::MovWord, OperandValue::Register(_)) => Err("`movw` expects a constant, use `mov` to copy a register".to_string()),
+        (Mnemonic::MovWord, OperandValue::Number(number)) => {
+            if !WORD_RANGE.contains(&number) {
+                return Err(format!("value {number} does not fit in 16 bits"));
+            }
+            Ok(vec![
+                Command { command_type: CommandType::MOV, operand: Operand::Literal(number as u16 & 0xff) },
+                Command { command_type: CommandType::MOVH, operand: Operand::Literal(number as u16 >> 8) },
+            ])
+        }
     }
-}
\ No newline at end of file
+}
+
+fn parse_operand<'a>(token: Token<'a>, symbols: &HashMap<&str, Symbol>) -> Result<OperandValue, SymbolError<'a>> {
+    match RegisterName::from_str(token.text) {
+        Ok(register) => Ok(OperandValue::Register(register)),
+        Err(_) => evaluate(token, symbols).map(OperandValue::Number)
+    }
+}
+
+fn evaluate<'a>(token: Token<'a>, symbols: &HashMap<&str, Symbol>) -> Result<i64, SymbolError<'a>> {
+    evaluate_with(token, symbols, |name| format!("`{name}` is not a number, register or defined symbol"))
+}
+
+// `.equ`, `.org` и `.fill` вычисляются на первом проходе и могут ссылаться только на символы, определённые выше
+fn evaluate_defined<'a>(token: Token<'a>, symbols: &HashMap<&str, Symbol>) -> Result<i64, SymbolError<'a>> {
+    evaluate_with(token, symbols, |name| format!("symbol `{name}` must be defined before use in this directive"))
+}
+
+fn evaluate_with<'a>(token: Token<'a>, symbols: &HashMap<&str, Symbol>, undefined: impl Fn(&str) -> String) -> Result<i64, SymbolError<'a>> {
+    let lookup = |name: &str| {
+        if RegisterName::from_str(name).is_ok() {
+            Err(format!("register `{name}` cannot be used in an expression"))
+        } else {
+            symbols.get(name).map(|v| v.value).ok_or_else(|| undefined(name))
+        }
+    };
+
+    eval_expr(token.text, &lookup).map_err(|e| (Token {
+        text: &token.text[e.offset.min(token.text.len())..(e.offset + e.length).min(token.text.len())],
+        offset: token.offset + e.offset.min(token.text.len())
+    }, e.message))
+}
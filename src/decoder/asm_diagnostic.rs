@@ -0,0 +1,62 @@
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub message: String,
+    pub source_line: String,
+    // дополнительные места, например вызовы макросов, в раскрытии которых возникла ошибка
+    pub notes: Vec<Diagnostic>
+}
+
+impl Diagnostic {
+
+    pub fn new(file: &str, line: usize, source_line: &str, offset: usize, length: usize, message: String) -> Diagnostic {
+        Diagnostic {
+            file: file.to_string(),
+            line,
+            column: source_line[..offset].chars().count() + 1,
+            length: source_line[offset..offset + length].chars().count().max(1),
+            message,
+            source_line: source_line.trim_end().to_string(),
+            notes: Vec::new()
+        }
+    }
+
+    // ключ сортировки: позиции от самого внешнего вызова макроса или включения файла до места ошибки
+    pub fn position(&self) -> Vec<(usize, usize)> {
+        self.notes.iter().rev()
+            .chain(std::iter::once(self))
+            .map(|v| (v.line, v.column))
+            .collect()
+    }
+
+    pub fn render(&self) -> String {
+        let mut res = self.render_as("error");
+        for note in &self.notes {
+            res += &note.render_as("note");
+        }
+        res
+    }
+
+    fn render_as(&self, level: &str) -> String {
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        // табуляция сохраняется, чтобы каретка оказалась под нужным символом
+        let caret_indent = self.source_line.chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        format!(
+            "{level}: {}\n{gutter}--> {}:{}:{}\n{gutter} |\n{line_number} | {}\n{gutter} | {caret_indent}{}\n",
+            self.message,
+            self.file,
+            self.line,
+            self.column,
+            self.source_line,
+            "^".repeat(self.length)
+        )
+    }
+}
\ No newline at end of file
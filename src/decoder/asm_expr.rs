@@ -0,0 +1,288 @@
+// Вычисление константных выражений ассемблера.
+// Приоритет операций (по возрастанию): `|`, `&`, `<< >>`, `+ -`, `* /`, унарный `-`;
+// `hi(x)` и `lo(x)` возвращают старший и младший байт 16-битного значения.
+// Числа записываются в десятичном виде, с префиксом `0x` (шестнадцатеричные), `0b` (двоичные)
+// или символом в одинарных кавычках: `'A'`, `'\n'`.
+
+pub struct ExprError {
+    pub offset: usize,
+    pub length: usize,
+    pub message: String
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ExprTokenKind {
+    Number(i64),
+    Ident,
+    Operator,
+    OpenParen,
+    CloseParen,
+}
+
+#[derive(Copy, Clone)]
+struct ExprToken<'a> {
+    kind: ExprTokenKind,
+    text: &'a str,
+    offset: usize,
+}
+
+const OPERATORS: [&str; 8] = ["<<", ">>", "+", "-", "*", "/", "&", "|"];
+
+// уровни приоритета бинарных операций, от низшего к высшему
+const PRECEDENCE: [&[&str]; 5] = [&["|"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/"]];
+
+pub fn eval_expr(text: &str, lookup: &dyn Fn(&str) -> Result<i64, String>) -> Result<i64, ExprError> {
+    let tokens = lex(text)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        text_len: text.len(),
+        lookup
+    };
+
+    let value = parser.parse_binary(0)?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.error_at(token, format!("unexpected `{}` in expression", token.text)));
+    }
+    Ok(value)
+}
+
+fn lex(text: &str) -> Result<Vec<ExprToken<'_>>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    while offset < text.len() {
+        let rest = &text[offset..];
+        let c = rest.chars().next().unwrap();
+
+        let (kind, len) = if c.is_whitespace() {
+            offset += c.len_utf8();
+            continue;
+        } else if c.is_ascii_digit() {
+            let len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            let value = parse_number(&rest[..len]).ok_or_else(|| ExprError {
+                offset,
+                length: len,
+                message: format!("invalid number `{}`", &rest[..len])
+            })?;
+            (ExprTokenKind::Number(value), len)
+        } else if c == '\'' {
+            let (value, len) = parse_char(rest).map_err(|(length, message)| ExprError { offset, length, message })?;
+            (ExprTokenKind::Number(value), len)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            (ExprTokenKind::Ident, rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len()))
+        } else if c == '(' {
+            (ExprTokenKind::OpenParen, 1)
+        } else if c == ')' {
+            (ExprTokenKind::CloseParen, 1)
+        } else if let Some(operator) = OPERATORS.iter().find(|v| rest.starts_with(**v)) {
+            (ExprTokenKind::Operator, operator.len())
+        } else {
+            return Err(ExprError {
+                offset,
+                length: c.len_utf8(),
+                message: format!("unexpected character `{c}` in expression")
+            });
+        };
+
+        tokens.push(ExprToken { kind, text: &rest[..len], offset });
+        offset += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a, 't> {
+    tokens: &'t [ExprToken<'a>],
+    position: usize,
+    text_len: usize,
+    lookup: &'t dyn Fn(&str) -> Result<i64, String>
+}
+
+impl<'a> Parser<'a, '_> {
+
+    fn peek(&self) -> Option<ExprToken<'a>> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<ExprToken<'a>, ExprError> {
+        let token = self.peek().ok_or(ExprError {
+            offset: self.text_len,
+            length: 1,
+            message: "unexpected end of expression".to_string()
+        })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn error_at(&self, token: ExprToken, message: String) -> ExprError {
+        ExprError {
+            offset: token.offset,
+            length: token.text.len(),
+            message
+        }
+    }
+
+    fn parse_binary(&mut self, level: usize) -> Result<i64, ExprError> {
+        if level == PRECEDENCE.len() {
+            return self.parse_primary();
+        }
+
+        let mut value = self.parse_binary(level + 1)?;
+        while let Some(operator) = self.peek().filter(|v| v.kind == ExprTokenKind::Operator && PRECEDENCE[level].contains(&v.text)) {
+            self.position += 1;
+            let rhs = self.parse_binary(level + 1)?;
+            value = apply_operator(operator.text, value, rhs).ok_or_else(|| self.error_at(operator, match operator.text {
+                "/" if rhs == 0 => "division by zero in expression".to_string(),
+                "<<" | ">>" => format!("invalid shift amount {rhs}"),
+                _ => "arithmetic overflow in expression".to_string()
+            }))?;
+        }
+        Ok(value)
+    }
+
+    fn parse_primary(&mut self) -> Result<i64, ExprError> {
+        let token = self.next()?;
+        match token.kind {
+            ExprTokenKind::Number(value) => Ok(value),
+            ExprTokenKind::OpenParen => {
+                let value = self.parse_binary(0)?;
+                self.expect_close_paren()?;
+                Ok(value)
+            }
+            ExprTokenKind::Ident if self.peek().is_some_and(|v| v.kind == ExprTokenKind::OpenParen) => {
+                self.position += 1;
+                let argument = self.parse_binary(0)?;
+                self.expect_close_paren()?;
+                match token.text.to_ascii_lowercase().as_str() {
+                    "hi" => Ok((argument >> 8) & 0xff),
+                    "lo" => Ok(argument & 0xff),
+                    _ => Err(self.error_at(token, format!("unknown function `{}`", token.text)))
+                }
+            }
+            ExprTokenKind::Ident => (self.lookup)(token.text).map_err(|message| self.error_at(token, message)),
+            ExprTokenKind::Operator if token.text == "-" => {
+                let value = self.parse_primary()?;
+                value.checked_neg().ok_or_else(|| self.error_at(token, "arithmetic overflow in expression".to_string()))
+            }
+            ExprTokenKind::Operator | ExprTokenKind::CloseParen => Err(self.error_at(token, format!("expected value, found `{}`", token.text)))
+        }
+    }
+
+    fn expect_close_paren(&mut self) -> Result<(), ExprError> {
+        let token = self.next()?;
+        if token.kind != ExprTokenKind::CloseParen {
+            return Err(self.error_at(token, format!("expected `)`, found `{}`", token.text)));
+        }
+        Ok(())
+    }
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse::<i64>().ok()
+    }
+}
+
+// символьный литерал в начале текста: значение и длина вместе с кавычками
+fn parse_char(text: &str) -> Result<(i64, usize), (usize, String)> {
+    let mut chars = text.char_indices().skip(1);
+    let value = match chars.next() {
+        Some((_, '\\')) => match chars.next() {
+            Some((_, 'n')) => '\n',
+            Some((_, 't')) => '\t',
+            Some((_, '0')) => '\0',
+            Some((_, '\\')) => '\\',
+            Some((_, '\'')) => '\'',
+            Some((index, other)) => return Err((index + other.len_utf8(), format!("unknown escape sequence `\\{other}`"))),
+            None => return Err((text.len(), "unterminated character literal".to_string()))
+        },
+        Some((_, '\'')) => return Err((2, "empty character literal".to_string())),
+        Some((_, c)) => c,
+        None => return Err((text.len(), "unterminated character literal".to_string()))
+    };
+
+    match chars.next() {
+        Some((index, '\'')) if value.is_ascii() => Ok((value as i64, index + 1)),
+        Some((index, '\'')) => Err((index + 1, format!("non-ASCII character `{value}` in character literal"))),
+        _ => match text[1..].find('\'') {
+            Some(index) => Err((index + 2, "character literal must contain exactly one character".to_string())),
+            None => Err((text.len(), "unterminated character literal".to_string()))
+        }
+    }
+}
+
+fn apply_operator(operator: &str, lhs: i64, rhs: i64) -> Option<i64> {
+    match operator {
+        "+" => lhs.checked_add(rhs),
+        "-" => lhs.checked_sub(rhs),
+        "*" => lhs.checked_mul(rhs),
+        "/" => lhs.checked_div(rhs),
+        "<<" => u32::try_from(rhs).ok().filter(|v| *v < 63).and_then(|v| lhs.checked_shl(v)),
+        ">>" => u32::try_from(rhs).ok().filter(|v| *v < 63).and_then(|v| lhs.checked_shr(v)),
+        "&" => Some(lhs & rhs),
+        "|" => Some(lhs | rhs),
+        _ => None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str) -> Result<i64, String> {
+        let lookup = |name: &str| match name {
+            "SIZE" => Ok(8),
+            "BASE" => Ok(0x100),
+            _ => Err(format!("`{name}` is not defined"))
+        };
+        eval_expr(text, &lookup).map_err(|e| e.message)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9));
+        assert_eq!(eval("10 - 4 - 3"), Ok(3));
+        assert_eq!(eval("1 << 2 + 1"), Ok(8));
+        assert_eq!(eval("1 | 2 & 3"), Ok(3));
+        assert_eq!(eval("0xf0 | 0x0f & 0x3"), Ok(0xf3));
+        assert_eq!(eval("-2 * 3"), Ok(-6));
+        assert_eq!(eval("BASE + (SIZE - 1) * 2"), Ok(0x10e));
+    }
+
+    #[test]
+    fn hi_lo() {
+        assert_eq!(eval("hi(0x1234)"), Ok(0x12));
+        assert_eq!(eval("lo(0x1234)"), Ok(0x34));
+        assert_eq!(eval("LO(350) | HI(350) << 8"), Ok(350));
+        assert_eq!(eval("hi(-1)"), Ok(0xff));
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(eval("0b1010 + 0x10 + 'A'"), Ok(10 + 16 + 65));
+        assert_eq!(eval("'\\n'"), Ok(10));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(eval("1 / 0"), Err("division by zero in expression".to_string()));
+        assert_eq!(eval("1 +"), Err("unexpected end of expression".to_string()));
+        assert_eq!(eval("(1"), Err("unexpected end of expression".to_string()));
+        assert_eq!(eval("1 2"), Err("unexpected `2` in expression".to_string()));
+        assert_eq!(eval("foo(1)"), Err("unknown function `foo`".to_string()));
+        assert_eq!(eval("UNKNOWN"), Err("`UNKNOWN` is not defined".to_string()));
+    }
+
+    #[test]
+    fn error_position() {
+        let lookup = |_: &str| Ok(0);
+        let error = eval_expr("1 + $", &lookup).err().unwrap();
+        assert_eq!((error.offset, error.length), (4, 1));
+    }
+}
\ No newline at end of file
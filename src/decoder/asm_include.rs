@@ -0,0 +1,195 @@
+use crate::decoder::asm_diagnostic::Diagnostic;
+use crate::decoder::asm_lexer::tokenize;
+use crate::decoder::asm_source::{FrameKind, LineRole, SourceLine};
+use crate::file_loaders::load_string_file;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Включение файлов: `.include "путь"` подставляет строки файла на место директивы до раскрытия макросов.
+// Путь ищется относительно включающего файла, затем в каталогах из `include_paths`.
+
+struct Include {
+    offset: usize,
+    length: usize,
+    name: String,
+}
+
+pub fn include_files(lines: Vec<SourceLine>, include_paths: &[PathBuf], diagnostics: &mut Vec<Diagnostic>) -> Vec<SourceLine> {
+    // цепочка включений от основного файла, для обнаружения циклов
+    let mut stack = lines.first()
+        .map(|v| canonical(Path::new(&*v.file)))
+        .into_iter()
+        .collect();
+
+    let mut res = Vec::new();
+    process(lines, include_paths, &mut stack, &mut res, diagnostics);
+    res
+}
+
+fn process(lines: Vec<SourceLine>, include_paths: &[PathBuf], stack: &mut Vec<PathBuf>, res: &mut Vec<SourceLine>, diagnostics: &mut Vec<Diagnostic>) {
+    for line in lines {
+        let include = match parse_include(&line) {
+            None => {
+                res.push(line);
+                continue;
+            }
+            Some(Ok(include)) => include,
+            Some(Err(diagnostic)) => {
+                diagnostics.push(diagnostic);
+                continue;
+            }
+        };
+        let error = |message: String| line.diagnostic(include.offset, include.length, message);
+
+        let Some(path) = resolve(&line.file, &include.name, include_paths) else {
+            diagnostics.push(error(format!("cannot find include file `{}`", include.name)));
+            continue;
+        };
+        let canonical_path = canonical(&path);
+        if stack.contains(&canonical_path) {
+            diagnostics.push(error(format!("recursive include of `{}`", include.name)));
+            continue;
+        }
+        let path_name = path.to_string_lossy();
+        let text = match load_string_file(&path_name) {
+            Ok(text) => text,
+            Err(e) => {
+                diagnostics.push(error(format!("cannot read `{path_name}`: {e}")));
+                continue;
+            }
+        };
+
+        // метки перед директивой относятся к первой строке включённого файла
+        res.push(line.with_role(LineRole::LabelsOnly));
+
+        let frame = line.frame(FrameKind::Include, include.offset, include.length);
+        let included = SourceLine::from_file(&path_name, &text).into_iter()
+            .map(|v| SourceLine {
+                expansion: std::iter::once(frame.clone()).chain(line.expansion.iter().cloned()).collect(),
+                ..v
+            })
+            .collect();
+
+        stack.push(canonical_path);
+        process(included, include_paths, stack, res, diagnostics);
+        stack.pop();
+    }
+}
+
+// `None`, если строка не содержит директивы `.include`
+fn parse_include(line: &SourceLine) -> Option<Result<Include, Diagnostic>> {
+    let (_, mut tokens) = tokenize(&line.text);
+    let directive = tokens.next().filter(|v| v.text.eq_ignore_ascii_case(".include"))?;
+
+    let Some(name) = tokens.next() else {
+        return Some(Err(line.diagnostic(directive.offset, directive.text.len(), "expected file name in quotes after `.include`".to_string())));
+    };
+    let Some(file_name) = name.text.strip_prefix('"').and_then(|v| v.strip_suffix('"')).filter(|v| !v.is_empty()) else {
+        return Some(Err(line.diagnostic(name.offset, name.text.len(), format!("expected file name in quotes, found `{}`", name.text))));
+    };
+    if let Some(extra) = tokens.next() {
+        return Some(Err(line.diagnostic(extra.offset, extra.text.len(), format!("unexpected `{}` after file name", extra.text))));
+    }
+
+    Some(Ok(Include {
+        offset: directive.offset,
+        length: name.offset + name.text.len() - directive.offset,
+        name: file_name.to_string()
+    }))
+}
+
+fn resolve(including_file: &str, name: &str, include_paths: &[PathBuf]) -> Option<PathBuf> {
+    let base = Path::new(including_file).parent().unwrap_or(Path::new(""));
+    std::iter::once(base.join(name))
+        .chain(include_paths.iter().map(|v| v.join(name)))
+        .find(|v| v.is_file())
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // каталог с файлами теста, удаляется при завершении
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+
+        fn new(name: &str, files: &[(&str, &str)]) -> TestDir {
+            let dir = std::env::temp_dir().join(format!("vm_include_{name}_{}", std::process::id()));
+            for (path, text) in files {
+                let path = dir.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, text).unwrap();
+            }
+            TestDir(dir)
+        }
+
+        fn include(&self, main: &str, include_paths: &[&str]) -> (Vec<String>, Vec<String>) {
+            let path = self.0.join(main).to_string_lossy().to_string();
+            let lines = load_string_file(&path).unwrap();
+            let include_paths = include_paths.iter().map(|v| self.0.join(v)).collect::<Vec<_>>();
+
+            let mut diagnostics = Vec::new();
+            let included = include_files(SourceLine::from_file(&path, &lines), &include_paths, &mut diagnostics);
+            let code = included.into_iter()
+                .filter(|v| v.role == LineRole::Code && !v.text.is_empty())
+                .map(|v| v.text)
+                .collect();
+            (code, diagnostics.into_iter().map(|v| v.message).collect())
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn includes_relative_files_and_include_paths() {
+        let dir = TestDir::new("paths", &[
+            ("prog/main.asm", ".include \"local.asm\"\n.include \"math.asm\"\nhalt"),
+            ("prog/local.asm", "nop"),
+            ("lib/math.asm", "add 1"),
+        ]);
+
+        let (code, diagnostics) = dir.include("prog/main.asm", &["lib"]);
+        assert!(diagnostics.is_empty());
+        assert_eq!(code, ["nop", "add 1", "halt"]);
+
+        let (_, diagnostics) = dir.include("prog/main.asm", &[]);
+        assert_eq!(diagnostics, ["cannot find include file `math.asm`"]);
+    }
+
+    #[test]
+    fn detects_include_cycles() {
+        let dir = TestDir::new("cycle", &[
+            ("a.asm", ".include \"b.asm\"\nhalt"),
+            ("b.asm", "nop\n.include \"a.asm\""),
+            ("self.asm", ".include \"self.asm\""),
+        ]);
+
+        let (code, diagnostics) = dir.include("a.asm", &[]);
+        assert_eq!(diagnostics, ["recursive include of `a.asm`"]);
+        assert_eq!(code, ["nop", "halt"]);
+
+        let (_, diagnostics) = dir.include("self.asm", &[]);
+        assert_eq!(diagnostics, ["recursive include of `self.asm`"]);
+    }
+
+    #[test]
+    fn same_file_may_be_included_twice_without_cycle() {
+        let dir = TestDir::new("twice", &[
+            ("main.asm", ".include \"lib.asm\"\n.include \"lib.asm\""),
+            ("lib.asm", "nop"),
+        ]);
+
+        let (code, diagnostics) = dir.include("main.asm", &[]);
+        assert!(diagnostics.is_empty());
+        assert_eq!(code, ["nop", "nop"]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,110 @@
+#[derive(Copy, Clone)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub offset: usize,
+}
+
+// разбивает строку на метки и остальные токены, отбрасывая комментарий;
+// запятая — отдельный токен, строка в кавычках — один токен
+pub fn tokenize(line: &str) -> (Vec<Token<'_>>, impl Iterator<Item = Token<'_>>) {
+    let mut tokens = Vec::new();
+    let mut rest = line;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() || rest.starts_with("//") {
+            break;
+        }
+
+        let len = if rest.starts_with(',') {
+            1
+        } else if rest.starts_with(['"', '\'']) {
+            quoted_len(rest)
+        } else {
+            rest.find(|c: char| c.is_whitespace() || c == ',' || c == '"' || c == '\'')
+                .into_iter()
+                .chain(rest.find("//"))
+                .min()
+                .unwrap_or(rest.len())
+        };
+
+        tokens.push(Token {
+            text: &rest[..len],
+            offset: rest.as_ptr() as usize - line.as_ptr() as usize
+        });
+        rest = &rest[len..];
+    }
+
+    let mut labels = Vec::new();
+    let mut rest = Vec::new();
+    for token in tokens {
+        if !rest.is_empty() || token.text.starts_with(['"', '\'']) {
+            rest.push(token);
+            continue;
+        }
+
+        let mut token = token;
+        while let Some(index) = token.text.find(':') {
+            labels.push(Token { text: &token.text[..index], offset: token.offset });
+            token = Token { text: &token.text[index + 1..], offset: token.offset + index + 1 };
+        }
+        if !token.text.is_empty() {
+            rest.push(token);
+        }
+    }
+
+    (labels, rest.into_iter())
+}
+
+// длина строкового или символьного литерала вместе с кавычками; незакрытый литерал продолжается до конца строки
+pub fn quoted_len(text: &str) -> usize {
+    let quote = text.chars().next().unwrap();
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return index + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+pub fn is_valid_label(label: &str) -> bool {
+    let mut chars = label.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// аргументы директивы разделяются запятыми, каждый аргумент может состоять из нескольких токенов
+pub fn parse_args<'a>(line: &'a str, tokens: impl Iterator<Item = Token<'a>>) -> Result<Vec<Token<'a>>, (Token<'a>, String)> {
+    let mut args = Vec::new();
+    let mut current = Vec::new();
+    let mut last = None;
+    for token in tokens {
+        if token.text == "," {
+            let arg = span(line, &current).ok_or((token, "expected value before `,`".to_string()))?;
+            args.push(arg);
+            current.clear();
+        } else {
+            current.push(token);
+        }
+        last = Some(token);
+    }
+
+    match (span(line, &current), last) {
+        (Some(arg), _) => args.push(arg),
+        (None, Some(token)) => return Err((token, "expected value after `,`".to_string())),
+        (None, None) => {}
+    }
+    Ok(args)
+}
+
+// объединяет соседние токены строки в один
+pub fn span<'a>(line: &'a str, tokens: &[Token<'a>]) -> Option<Token<'a>> {
+    let (first, last) = (tokens.first()?, tokens.last()?);
+    Some(Token {
+        text: &line[first.offset..last.offset + last.text.len()],
+        offset: first.offset
+    })
+}
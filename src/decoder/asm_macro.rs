@@ -0,0 +1,368 @@
+use crate::command::CommandType;
+use crate::decoder::asm_diagnostic::Diagnostic;
+use crate::decoder::asm_lexer::{is_valid_label, parse_args, quoted_len, tokenize, Token};
+use crate::decoder::asm_source::{FrameKind, LineRole, SourceLine};
+use crate::register::RegisterName;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// Макросы: `.macro имя параметр1, параметр2` ... `.endm`.
+// Параметры подставляются в тело по имени, метки внутри тела локальны для каждого раскрытия.
+
+// глубина вложенных раскрытий, после которой макрос считается рекурсивным
+const MAX_EXPANSION_DEPTH: usize = 64;
+// общее число раскрытий: макрос, вызывающий себя несколько раз, растёт экспоненциально раньше, чем достигнет предела глубины
+const MAX_EXPANSIONS: usize = 10000;
+
+#[derive(Clone)]
+struct Macro {
+    params: Vec<String>,
+    body: Vec<SourceLine>,
+    line_number: usize,
+}
+
+// первый токен строки определяет, что с ней делать
+enum LineKind {
+    Plain,
+    Definition,
+    End,
+    Invocation {
+        name: String,
+        offset: usize,
+    },
+}
+
+struct MacroExpander {
+    macros: HashMap<String, Macro>,
+    expansions: usize,
+    recursion_reported: bool,
+}
+
+pub fn expand_macros(lines: Vec<SourceLine>, diagnostics: &mut Vec<Diagnostic>) -> Vec<SourceLine> {
+    let mut expander = MacroExpander {
+        macros: HashMap::new(),
+        expansions: 0,
+        recursion_reported: false
+    };
+
+    let mut res = Vec::new();
+    expander.process(lines, &mut res, diagnostics);
+    res
+}
+
+impl MacroExpander {
+
+    fn process(&mut self, lines: Vec<SourceLine>, res: &mut Vec<SourceLine>, diagnostics: &mut Vec<Diagnostic>) {
+        let mut lines = lines.into_iter();
+        while let Some(line) = lines.next() {
+            match self.classify(&line, diagnostics) {
+                LineKind::Plain => res.push(line),
+                LineKind::Definition => self.define(&line, &mut lines, res, diagnostics),
+                LineKind::End => {
+                    let (_, mut tokens) = tokenize(&line.text);
+                    let token = tokens.next().unwrap();
+                    diagnostics.push(line.diagnostic(token.offset, token.text.len(), "`.endm` without matching `.macro`".to_string()));
+                }
+                LineKind::Invocation { name, offset } => {
+                    // метки перед вызовом относятся к первой команде раскрытия
+                    res.push(line.with_role(LineRole::LabelsOnly));
+                    self.expand(&line, &name, offset, res, diagnostics);
+                }
+            }
+        }
+    }
+
+    fn classify(&self, line: &SourceLine, diagnostics: &mut Vec<Diagnostic>) -> LineKind {
+        let (labels, mut tokens) = tokenize(&line.text);
+        let Some(first) = tokens.next().filter(|_| line.role == LineRole::Code) else {
+            return LineKind::Plain;
+        };
+
+        let is_definition = first.text.eq_ignore_ascii_case(".macro");
+        let is_end = first.text.eq_ignore_ascii_case(".endm");
+        if is_definition || is_end {
+            if let Some(label) = labels.first() {
+                diagnostics.push(line.diagnostic(label.offset, label.text.len(), format!("label is not allowed before `{}`", first.text)));
+            }
+            return if is_definition { LineKind::Definition } else { LineKind::End };
+        }
+
+        if !self.macros.contains_key(first.text) {
+            return LineKind::Plain;
+        }
+        LineKind::Invocation {
+            name: first.text.to_string(),
+            offset: first.offset
+        }
+    }
+
+    fn define(&mut self, line: &SourceLine, lines: &mut impl Iterator<Item = SourceLine>, res: &mut Vec<SourceLine>, diagnostics: &mut Vec<Diagnostic>) {
+        let (_, mut tokens) = tokenize(&line.text);
+        let keyword = tokens.next().unwrap();
+        let error = |token: Token, message: String| line.diagnostic(token.offset, token.text.len(), message);
+
+        // тело собирается даже при ошибке в заголовке, чтобы не разбирать его как обычный код
+        let mut body = Vec::new();
+        let mut terminated = false;
+        res.push(line.with_role(LineRole::ListingOnly));
+        for body_line in lines.by_ref() {
+            res.push(body_line.with_role(LineRole::ListingOnly));
+            let (_, mut body_tokens) = tokenize(&body_line.text);
+            match body_tokens.next() {
+                Some(token) if token.text.eq_ignore_ascii_case(".endm") => {
+                    terminated = true;
+                    break;
+                }
+                Some(token) if token.text.eq_ignore_ascii_case(".macro") => {
+                    diagnostics.push(body_line.diagnostic(token.offset, token.text.len(), "nested macro definitions are not supported".to_string()));
+                }
+                _ => body.push(body_line.clone())
+            }
+        }
+
+        let Some(name) = tokens.next() else {
+            diagnostics.push(error(keyword, "missing macro name".to_string()));
+            return;
+        };
+        if !terminated {
+            diagnostics.push(error(name, format!("unterminated macro `{}`, missing `.endm`", name.text)));
+            return;
+        }
+        if !is_valid_label(name.text) {
+            diagnostics.push(error(name, format!("invalid macro name `{}`", name.text)));
+            return;
+        }
+        if CommandType::from_str(name.text).is_ok() || name.text.eq_ignore_ascii_case("movw") {
+            diagnostics.push(error(name, format!("macro `{}` conflicts with instruction name", name.text)));
+            return;
+        }
+        if let Some(defined) = self.macros.get(name.text) {
+            diagnostics.push(error(name, format!("duplicate macro `{}` (first defined at line {})", name.text, defined.line_number)));
+            return;
+        }
+
+        let params = match parse_args(&line.text, tokens) {
+            Ok(params) => params,
+            Err((token, message)) => {
+                diagnostics.push(error(token, message));
+                return;
+            }
+        };
+        for (index, param) in params.iter().enumerate() {
+            let message = if !is_valid_label(param.text) {
+                format!("invalid macro parameter `{}`", param.text)
+            } else if RegisterName::from_str(param.text).is_ok() || CommandType::from_str(param.text).is_ok() {
+                format!("macro parameter `{}` conflicts with register or instruction name", param.text)
+            } else if params[..index].iter().any(|v| v.text == param.text) {
+                format!("duplicate macro parameter `{}`", param.text)
+            } else {
+                continue;
+            };
+            diagnostics.push(error(*param, message));
+            return;
+        }
+
+        self.macros.insert(name.text.to_string(), Macro {
+            params: params.iter().map(|v| v.text.to_string()).collect(),
+            body,
+            line_number: line.line_number
+        });
+    }
+
+    fn expand(&mut self, line: &SourceLine, name: &str, offset: usize, res: &mut Vec<SourceLine>, diagnostics: &mut Vec<Diagnostic>) {
+        let definition = self.macros[name].clone();
+        let error = |message: String| line.diagnostic(offset, name.len(), message);
+
+        let (_, mut tokens) = tokenize(&line.text);
+        tokens.next();
+        let args = match parse_args(&line.text, tokens) {
+            Ok(args) => args,
+            Err((token, message)) => {
+                diagnostics.push(line.diagnostic(token.offset, token.text.len(), message));
+                return;
+            }
+        };
+        if args.len() != definition.params.len() {
+            diagnostics.push(error(format!("macro `{name}` takes {} argument(s) but {} were given", definition.params.len(), args.len())));
+            return;
+        }
+        // после ошибки рекурсии остальные раскрытия только повторили бы её
+        if self.recursion_reported {
+            return;
+        }
+        let macro_frames = line.expansion.iter().filter(|v| matches!(v.kind, FrameKind::Macro(_))).count();
+        let reason = if macro_frames >= MAX_EXPANSION_DEPTH {
+            Some("macro expansion is too deep")
+        } else if self.expansions >= MAX_EXPANSIONS {
+            Some("too many macro expansions")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            self.recursion_reported = true;
+            diagnostics.push(recursion_error(line, offset, name, reason));
+            return;
+        }
+
+        self.expansions += 1;
+        let mut replacements = definition.params.iter().cloned()
+            .zip(args.iter().map(|v| v.text.to_string()))
+            .collect::<HashMap<_, _>>();
+        // метки и константы тела локальны для каждого раскрытия
+        for body_line in &definition.body {
+            for local in local_symbols(&body_line.text) {
+                replacements.insert(local.to_string(), format!("__{name}_{}_{local}", self.expansions));
+            }
+        }
+
+        let frame = line.frame(FrameKind::Macro(name.to_string()), offset, name.len());
+        let expanded = definition.body.iter()
+            .map(|body_line| SourceLine {
+                file: body_line.file.clone(),
+                text: substitute(&body_line.text, &replacements),
+                line_number: body_line.line_number,
+                role: body_line.role,
+                expansion: std::iter::once(frame.clone()).chain(line.expansion.iter().cloned()).collect()
+            })
+            .collect();
+
+        self.process(expanded, res, diagnostics);
+    }
+}
+
+// имена меток и констант `.equ`/`.const`, определённых в строке
+fn local_symbols(text: &str) -> Vec<&str> {
+    let (labels, mut tokens) = tokenize(text);
+    let mut res = labels.iter().map(|v| v.text).collect::<Vec<_>>();
+    let is_constant = tokens.next().is_some_and(|v| v.text.eq_ignore_ascii_case(".equ") || v.text.eq_ignore_ascii_case(".const"));
+    if is_constant && let Some(name) = parse_args(text, tokens).ok().and_then(|args| args.first().copied()) {
+        res.push(name.text);
+    }
+    res
+}
+
+// вся цепочка раскрытий бесполезна, достаточно внешнего вызова
+fn recursion_error(line: &SourceLine, offset: usize, name: &str, reason: &str) -> Diagnostic {
+    let outermost = line.expansion.iter().rposition(|v| matches!(v.kind, FrameKind::Macro(_)));
+    let outermost = SourceLine {
+        expansion: outermost.map(|v| line.expansion[v..].to_vec()).unwrap_or_default(),
+        ..line.clone()
+    };
+    outermost.diagnostic(offset, name.len(), format!("{reason}, `{name}` is probably recursive"))
+}
+
+// заменяет имена параметров и локальных меток, не затрагивая литералы, комментарии и директивы
+fn substitute(text: &str, replacements: &HashMap<String, String>) -> String {
+    let mut res = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with("//") {
+            rest.len()
+        } else if c == '"' || c == '\'' {
+            quoted_len(rest)
+        } else if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            let len = rest[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '_').map_or(rest.len(), |v| v + 1);
+            if let Some(replacement) = replacements.get(&rest[..len]) {
+                res.push_str(replacement);
+                rest = &rest[len..];
+                continue;
+            }
+            len
+        } else {
+            c.len_utf8()
+        };
+
+        res.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(source: &str) -> (Vec<String>, Vec<String>) {
+        let lines = source.lines().map(|v| v.to_string()).collect::<Vec<_>>();
+        let mut diagnostics = Vec::new();
+        let expanded = expand_macros(SourceLine::from_file("test.asm", &lines), &mut diagnostics);
+        let code = expanded.into_iter()
+            .filter(|v| v.role == LineRole::Code)
+            .map(|v| v.text.trim().to_string())
+            .collect();
+        (code, diagnostics.into_iter().map(|v| v.message).collect())
+    }
+
+    #[test]
+    fn substitute_skips_literals_comments_and_directives() {
+        let replacements = HashMap::from([
+            ("n".to_string(), "rg1".to_string()),
+            ("word".to_string(), "X".to_string()),
+        ]);
+        assert_eq!(substitute("add n // n", &replacements), "add rg1 // n");
+        assert_eq!(substitute(".word n, 'n', \"n\"", &replacements), ".word rg1, 'n', \"n\"");
+        assert_eq!(substitute("mov 0xn + nn + n_1 + (n)", &replacements), "mov 0xn + nn + n_1 + (rg1)");
+    }
+
+    #[test]
+    fn parameters_and_nested_calls() {
+        let (code, diagnostics) = expand("\
+.macro add_to reg, n
+mov reg
+add n
+lrg reg
+.endm
+.macro twice reg
+add_to reg, 1
+add_to reg, SIZE - 1
+.endm
+twice rg2");
+        assert!(diagnostics.is_empty());
+        assert_eq!(code, ["mov rg2", "add 1", "lrg rg2", "mov rg2", "add SIZE - 1", "lrg rg2"]);
+    }
+
+    #[test]
+    fn hex_like_label_and_macro_name() {
+        let (code, diagnostics) = expand("\
+.macro add1 reg
+add reg
+.endm
+beef: add1 rg1");
+        assert!(diagnostics.is_empty());
+        assert_eq!(code, ["add rg1"]);
+    }
+
+    #[test]
+    fn labels_and_constants_are_local_to_expansion() {
+        let (code, diagnostics) = expand("\
+.macro wait reg
+.equ STEP, 1
+loop: sub STEP
+jnz loop
+.endm
+wait rg1
+wait rg2");
+        assert!(diagnostics.is_empty());
+        assert_eq!(code, [
+            ".equ __wait_1_STEP, 1", "__wait_1_loop: sub __wait_1_STEP", "jnz __wait_1_loop",
+            ".equ __wait_2_STEP, 1", "__wait_2_loop: sub __wait_2_STEP", "jnz __wait_2_loop",
+        ]);
+    }
+
+    #[test]
+    fn argument_count_mismatch() {
+        let (_, diagnostics) = expand(".macro m a, b\n.endm\nm 1");
+        assert_eq!(diagnostics, ["macro `m` takes 2 argument(s) but 1 were given"]);
+    }
+
+    #[test]
+    fn recursion_is_reported_once() {
+        let (_, diagnostics) = expand(".macro a\na\n.endm\na");
+        assert_eq!(diagnostics, ["macro expansion is too deep, `a` is probably recursive"]);
+
+        // каждое раскрытие вызывает макрос дважды: глубина растёт медленно, а число раскрытий — экспоненциально
+        let (_, diagnostics) = expand(".macro a\na\na\n.endm\na");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].ends_with("`a` is probably recursive"));
+    }
+}
\ No newline at end of file
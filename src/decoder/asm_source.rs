@@ -0,0 +1,90 @@
+use crate::decoder::asm_diagnostic::Diagnostic;
+use std::rc::Rc;
+
+// откуда взялась строка: раскрытие макроса или включение файла
+#[derive(Clone)]
+pub enum FrameKind {
+    Macro(String),
+    Include,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum LineRole {
+    // обычная строка программы
+    Code,
+    // директива `.include` или вызов макроса: содержимое уже подставлено, транслируются только метки
+    LabelsOnly,
+    // определение макроса: попадает только в листинг
+    ListingOnly,
+}
+
+// строка, из-за которой появилась текущая: вызов макроса или директива `.include`
+#[derive(Clone)]
+pub struct ExpansionFrame {
+    pub kind: FrameKind,
+    pub file: Rc<str>,
+    pub line_number: usize,
+    pub text: String,
+    pub offset: usize,
+    pub length: usize
+}
+
+// строка программы после включения файлов и раскрытия макросов вместе с её происхождением
+#[derive(Clone)]
+pub struct SourceLine {
+    pub file: Rc<str>,
+    pub text: String,
+    pub line_number: usize,
+    pub role: LineRole,
+    // вызовы макросов и включения файлов от внутреннего к внешнему
+    pub expansion: Vec<ExpansionFrame>
+}
+
+impl SourceLine {
+
+    pub fn from_file(file: &str, lines: &[String]) -> Vec<SourceLine> {
+        let file: Rc<str> = Rc::from(file);
+        lines.iter().enumerate()
+            .map(|(index, line)| SourceLine {
+                file: file.clone(),
+                text: line.clone(),
+                line_number: index + 1,
+                role: LineRole::Code,
+                expansion: Vec::new()
+            })
+            .collect()
+    }
+
+    pub fn with_role(&self, role: LineRole) -> SourceLine {
+        SourceLine {
+            role,
+            ..self.clone()
+        }
+    }
+
+    // кадр для строк, полученных из этой: `offset` и `length` указывают на имя макроса или директиву
+    pub fn frame(&self, kind: FrameKind, offset: usize, length: usize) -> ExpansionFrame {
+        ExpansionFrame {
+            kind,
+            file: self.file.clone(),
+            line_number: self.line_number,
+            text: self.text.clone(),
+            offset,
+            length
+        }
+    }
+
+    pub fn diagnostic(&self, offset: usize, length: usize, message: String) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(&self.file, self.line_number, &self.text, offset, length, message);
+        diagnostic.notes = self.expansion.iter()
+            .map(|frame| {
+                let message = match &frame.kind {
+                    FrameKind::Macro(name) => format!("in expansion of macro `{name}`"),
+                    FrameKind::Include => "in file included from here".to_string()
+                };
+                Diagnostic::new(&frame.file, frame.line_number, &frame.text, frame.offset, frame.length, message)
+            })
+            .collect();
+        diagnostic
+    }
+}
\ No newline at end of file
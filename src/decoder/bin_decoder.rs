@@ -1,13 +1,34 @@
 use crate::byte_formatter::byte_formatter::ByteFormatter;
+use crate::common::default_error::DefaultError;
 use crate::command::{Command, CommandType};
 use crate::operand::{Operand, OperandTag};
 use crate::register::{Register, RegisterName};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::rc::Rc;
 use enum_tags::TaggedEnum;
 use strum::IntoEnumIterator;
 
+#[derive(Debug, Copy, Clone)]
+pub enum DecodeError {
+    Command(u16),
+    OperandType(u16),
+    Register(u16),
+    SignedLiteral(u16)
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Command(id) => write!(f, "invalid command id {id}"),
+            DecodeError::OperandType(id) => write!(f, "invalid operand type id {id}"),
+            DecodeError::Register(id) => write!(f, "invalid register id {id}"),
+            DecodeError::SignedLiteral(value) => write!(f, "non-negative value {value} in signed literal operand")
+        }
+    }
+}
+
 pub fn parse_bin(bytes: &[u8], byte_formatter: Rc<dyn ByteFormatter>) -> Result<Vec<Command>, String> {
     if bytes.len() % 2 != 0 {
         return Err("invalid data".to_string());
@@ -16,18 +37,18 @@ pub fn parse_bin(bytes: &[u8], byte_formatter: Rc<dyn ByteFormatter>) -> Result<
     let mut res = Vec::new();
 
     for command in bytes.chunks(2) {
-        res.push(parse_command(command, byte_formatter.clone())?);
+        res.push(parse_command(command, byte_formatter.clone()).default_res()?);
     }
 
     Ok(res)
 }
 
-pub fn parse_command(bin: &[u8], byte_formatter: Rc<dyn ByteFormatter>) -> Result<Command, String> {
+pub fn parse_command(bin: &[u8], byte_formatter: Rc<dyn ByteFormatter>) -> Result<Command, DecodeError> {
     let bin2 = [bin[0], bin[1]];
     let bin16 = byte_formatter.wrap_bytes(bin2);
 
     let command_id = bin16 >> 10;
-    let command_type = *CODE_ID_MAPPING.get(&command_id).ok_or("invalid command id")?;
+    let command_type = *CODE_ID_MAPPING.get(&command_id).ok_or(DecodeError::Command(command_id))?;
 
     let operand = parse_operand(bin16)?;
 
@@ -37,14 +58,17 @@ pub fn parse_command(bin: &[u8], byte_formatter: Rc<dyn ByteFormatter>) -> Resul
     })
 }
 
-fn parse_operand(bin: u16) -> Result<Operand, String> {
+fn parse_operand(bin: u16) -> Result<Operand, DecodeError> {
     let operand_type_id = (bin >> 8) & 0b11;
     let value = bin & ((!0) >> 8);
 
-    let operand_tag = OPERAND_ID_MAPPING.get(&operand_type_id).ok_or("invalid operand type id")?;
+    let operand_tag = OPERAND_ID_MAPPING.get(&operand_type_id).ok_or(DecodeError::OperandType(operand_type_id))?;
     let operand = match operand_tag {
         OperandTag::Literal => Operand::Literal(value),
-        OperandTag::Register => Operand::Register(*REGISTER_ID_MAPPING.get(&value).ok_or("invalid register id")?)
+        OperandTag::Register => Operand::Register(*REGISTER_ID_MAPPING.get(&value).ok_or(DecodeError::Register(value))?),
+        // ассемблер кодирует неотрицательные числа как Literal, иначе слово дизассемблируется в другую команду
+        OperandTag::SignedLiteral if value < 0x80 => return Err(DecodeError::SignedLiteral(value)),
+        OperandTag::SignedLiteral => Operand::SignedLiteral(value as u8 as i8)
     };
 
     Ok(operand)
@@ -72,7 +96,7 @@ lazy_static! {
 
 fn calc_operand_mapping() -> HashMap<u16, OperandTag> {
     let mut res = HashMap::new();
-    for operand in [OperandTag::Register, OperandTag::Literal] {
+    for operand in [OperandTag::Register, OperandTag::Literal, OperandTag::SignedLiteral] {
         res.insert(operand.get_id(), operand);
     }
     res
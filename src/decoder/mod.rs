@@ -1,2 +1,8 @@
 pub mod asm_decoder;
+pub mod asm_diagnostic;
+pub mod asm_expr;
+pub mod asm_include;
+pub mod asm_lexer;
+pub mod asm_macro;
+pub mod asm_source;
 pub mod bin_decoder;
\ No newline at end of file
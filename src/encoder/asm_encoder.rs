@@ -0,0 +1,121 @@
+use crate::byte_formatter::byte_formatter::ByteFormatter;
+use crate::command::Command;
+use crate::decoder::bin_decoder::parse_command;
+use crate::operand::Operand;
+use std::rc::Rc;
+
+pub fn encode_asm(command: &Command) -> String {
+    let command_name: &str = command.command_type.into();
+    if !command.command_type.requires_operand() && matches!(command.operand, Operand::Literal(0)) {
+        return command_name.to_lowercase();
+    }
+
+    let operand = match command.operand {
+        Operand::Literal(v) => v.to_string(),
+        Operand::SignedLiteral(v) => v.to_string(),
+        Operand::Register(r) => <&str>::from(r).to_string()
+    };
+
+    format!("{} {}", command_name.to_lowercase(), operand.to_lowercase())
+}
+
+pub struct Disassembly {
+    pub lines: Vec<String>,
+    pub invalid_words: usize
+}
+
+pub fn disassemble(bytes: &[u8], load_address: u16, byte_formatter: Rc<dyn ByteFormatter>) -> Disassembly {
+    let mut lines = Vec::new();
+    let mut invalid_words = 0;
+
+    for (index, word) in bytes.chunks(2).enumerate() {
+        let address = load_address.wrapping_add(index as u16 * 2);
+        if word.len() < 2 {
+            lines.push(format!("// {:04X}: {:02X}    trailing byte", address, word[0]));
+            invalid_words += 1;
+            continue;
+        }
+
+        let raw = byte_formatter.wrap_bytes([word[0], word[1]]);
+        match parse_command(word, byte_formatter.clone()) {
+            // адрес и слово выводятся комментарием, поэтому строка собирается ассемблером без изменений
+            Ok(command) => lines.push(format!("{:<16}// {:04X}: {:04X}", encode_asm(&command), address, raw)),
+            Err(e) => {
+                lines.push(format!("// {:04X}: {:04X}  {}", address, raw, e));
+                invalid_words += 1;
+            }
+        }
+    }
+
+    Disassembly {
+        lines,
+        invalid_words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_formatter::endianness::Endianness;
+    use crate::decoder::asm_decoder::parse_asm;
+    use crate::decoder::asm_source::SourceLine;
+    use crate::encoder::bin_encoder::encode_command;
+
+    const PROGRAM: &str = "\
+.equ SIZE, 8
+start: movw 350
+    add -1
+    lrg rg1
+    push rg1
+    pop rg2
+    and 0x0F
+    not
+    shl 2
+loop: cmp SIZE
+    jnz loop
+    call sub
+    halt
+sub: ret";
+
+    fn compile(text: &str, endianness: Endianness) -> Vec<u8> {
+        let lines = text.lines().map(|v| v.to_string()).collect::<Vec<_>>();
+        let program = parse_asm(SourceLine::from_file("test.asm", &lines), &[], Some(endianness))
+            .unwrap_or_else(|diagnostics| panic!("{}", diagnostics[0].render()));
+        program.commands.iter()
+            .flat_map(|v| encode_command(v, endianness.formatter()))
+            .collect()
+    }
+
+    #[test]
+    fn disassembly_reassembles_to_identical_binary() {
+        for source in [PROGRAM, include_str!("../../example/max_v_asm"), include_str!("../../example/init_mem_asm")] {
+            for endianness in [Endianness::Little, Endianness::Big] {
+                let binary = compile(source, endianness);
+                let disassembly = disassemble(&binary, 0, endianness.formatter());
+                assert_eq!(disassembly.invalid_words, 0);
+                assert_eq!(compile(&disassembly.lines.join("\n"), endianness), binary);
+            }
+        }
+    }
+
+    #[test]
+    fn undecodable_words_are_commented_out() {
+        let disassembly = disassemble(&[0xff, 0xff, 0x00], 0, Endianness::Little.formatter());
+        assert_eq!(disassembly.invalid_words, 2);
+        assert!(disassembly.lines.iter().all(|v| v.starts_with("//")));
+    }
+
+    #[test]
+    fn non_negative_signed_literals_are_commented_out() {
+        // mov со SignedLiteral(5) и halt со SignedLiteral(0): ассемблер выдал бы 1D05 и 9100
+        for word in [0x1F05u16, 0x9300] {
+            let disassembly = disassemble(&word.to_le_bytes(), 0, Endianness::Little.formatter());
+            assert_eq!(disassembly.invalid_words, 1);
+            assert!(disassembly.lines.iter().all(|v| v.starts_with("//")));
+        }
+
+        let disassembly = disassemble(&0x1FFBu16.to_le_bytes(), 0, Endianness::Little.formatter());
+        assert_eq!(disassembly.invalid_words, 0);
+        assert!(disassembly.lines.iter().any(|v| v.starts_with("mov -5 ")));
+    }
+}
\ No newline at end of file
@@ -9,7 +9,8 @@ pub fn encode_command(command: &Command, byte_formatter: Rc<dyn ByteFormatter>)
     let operand_type = command.operand.tag().get_id();
     let operand_value = match command.operand {
         Operand::Literal(literal) => literal,
-        Operand::Register(name) => name.get_id()
+        Operand::Register(name) => name.get_id(),
+        Operand::SignedLiteral(literal) => literal as u8 as u16
     };
 
     let mut command = (cmd_type & 0b111111) << 10;
@@ -1 +1,2 @@
-pub mod bin_encoder;
\ No newline at end of file
+pub mod bin_encoder;
+pub mod asm_encoder;
\ No newline at end of file
@@ -0,0 +1,45 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Flags {
+    pub zero: bool,
+    pub negative: bool,
+    pub carry: bool,
+    pub overflow: bool
+}
+
+impl Flags {
+
+    pub fn from_result(result: u16, carry: bool, overflow: bool) -> Flags {
+        Flags {
+            zero: result == 0,
+            negative: result.cast_signed() < 0,
+            carry,
+            overflow
+        }
+    }
+
+    pub fn to_bits(self) -> u8 {
+        (self.zero as u8) | (self.negative as u8) << 1 | (self.carry as u8) << 2 | (self.overflow as u8) << 3
+    }
+
+    pub fn from_bits(bits: u8) -> Flags {
+        Flags {
+            zero: bits & 1 != 0,
+            negative: bits & 1 << 1 != 0,
+            carry: bits & 1 << 2 != 0,
+            overflow: bits & 1 << 3 != 0
+        }
+    }
+}
+
+impl Display for Flags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let flag = |set: bool, name: char| if set { name } else { '-' };
+        write!(f, "{}{}{}{}",
+               flag(self.zero, 'Z'),
+               flag(self.negative, 'N'),
+               flag(self.carry, 'C'),
+               flag(self.overflow, 'V'))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,219 @@
+use crate::flags::Flags;
+use crate::memory::data_memory::MemoryWrite;
+use crate::trace::{traced_step, RegisterChange};
+use crate::vm::{StepOutcome, VmState};
+use crate::vm_fault::VmFault;
+use std::collections::VecDeque;
+
+pub struct StepDelta {
+    pc: (u16, u16),
+    flags: (Flags, Flags),
+    halted: (bool, bool),
+    registers: Vec<RegisterChange>,
+    memory: Vec<MemoryWrite>
+}
+
+impl StepDelta {
+
+    fn is_empty(&self) -> bool {
+        self.pc.0 == self.pc.1
+            && self.flags.0 == self.flags.1
+            && self.halted.0 == self.halted.1
+            && self.registers.is_empty()
+            && self.memory.is_empty()
+    }
+
+    fn undo(&self, state: &mut VmState) {
+        for write in self.memory.iter().rev() {
+            state.data_memory.put(write.address, write.old);
+        }
+        for change in &self.registers {
+            state.registers.get_mut(change.name).unwrap().put_u16(change.old);
+        }
+        state.pc.pc = self.pc.0;
+        state.flags = self.flags.0;
+        state.halted = self.halted.0;
+    }
+
+    fn redo(&self, state: &mut VmState) {
+        for write in &self.memory {
+            state.data_memory.put(write.address, write.new);
+        }
+        for change in &self.registers {
+            state.registers.get_mut(change.name).unwrap().put_u16(change.new);
+        }
+        state.pc.pc = self.pc.1;
+        state.flags = self.flags.1;
+        state.halted = self.halted.1;
+    }
+}
+
+// история шагов для отладчика: отменённые шаги можно повторить, пока не выполнен новый шаг
+pub struct History {
+    undo: VecDeque<StepDelta>,
+    redo: Vec<StepDelta>,
+    capacity: usize,
+    steps: u64
+}
+
+impl History {
+
+    pub fn new(capacity: usize) -> History {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            capacity,
+            steps: 0
+        }
+    }
+
+    pub fn step(&mut self, state: &mut VmState) -> Result<StepOutcome, VmFault> {
+        let (flags, halted) = (state.flags, state.halted);
+        self.steps += 1;
+        let entry = traced_step(state, self.steps);
+
+        let delta = StepDelta {
+            pc: (entry.pc, state.pc.pc),
+            flags: (flags, state.flags),
+            halted: (halted, state.halted),
+            registers: entry.registers,
+            memory: entry.memory
+        };
+        if !delta.is_empty() {
+            self.redo.clear();
+            self.push(delta);
+        }
+
+        entry.outcome
+    }
+
+    pub fn step_back(&mut self, state: &mut VmState) -> bool {
+        let Some(delta) = self.undo.pop_back() else {
+            return false;
+        };
+
+        delta.undo(state);
+        self.redo.push(delta);
+        true
+    }
+
+    pub fn step_forward(&mut self, state: &mut VmState) -> bool {
+        let Some(delta) = self.redo.pop() else {
+            return false;
+        };
+
+        delta.redo(state);
+        self.push(delta);
+        true
+    }
+
+    pub fn depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push(&mut self, delta: StepDelta) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(delta);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_formatter::endianness::Endianness;
+    use crate::decoder::asm_decoder::parse_asm;
+    use crate::decoder::asm_source::SourceLine;
+    use crate::encoder::bin_encoder::encode_command;
+    use crate::memory::command_memory::CommandMemory;
+    use crate::register::RegisterName;
+    use crate::vm::{init_vm, VmConfig};
+
+    // шаги меняют ACC, память, флаги и признак останова
+    const PROGRAM: [&str; 4] = ["mov 7", "dac 16", "sub 8", "halt"];
+
+    fn load() -> VmState {
+        let lines = PROGRAM.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let program = parse_asm(SourceLine::from_file("test.asm", &lines), &[], None)
+            .unwrap_or_else(|diagnostics| panic!("{}", diagnostics[0].render()));
+        let bytes_formatter = Endianness::Little.formatter();
+        let code = program.commands.iter().flat_map(|v| encode_command(v, bytes_formatter.clone())).collect();
+        let command_memory = CommandMemory::load(code, 0, bytes_formatter.clone());
+        init_vm(bytes_formatter, command_memory, None, VmConfig::default()).unwrap()
+    }
+
+    fn observe(state: &VmState) -> (u16, u16, u8, String, bool) {
+        (state.pc.pc, state.registers.get(RegisterName::Acc).unwrap().as_u16(), state.data_memory.get(16), state.flags.to_string(), state.halted)
+    }
+
+    #[test]
+    fn undo_and_redo_restore_state() {
+        let mut state = load();
+        let mut history = History::new(10);
+        let mut observed = vec![observe(&state)];
+        for _ in PROGRAM {
+            history.step(&mut state).unwrap();
+            observed.push(observe(&state));
+        }
+        assert_eq!(observed.last().unwrap(), &(6, 0xffff, 7, "-NC-".to_string(), true));
+        assert_eq!(history.depth(), PROGRAM.len());
+
+        for expected in observed.iter().rev().skip(1) {
+            assert!(history.step_back(&mut state));
+            assert_eq!(&observe(&state), expected);
+        }
+        assert!(!history.step_back(&mut state));
+
+        for expected in observed.iter().skip(1) {
+            assert!(history.step_forward(&mut state));
+            assert_eq!(&observe(&state), expected);
+        }
+        assert!(!history.step_forward(&mut state));
+    }
+
+    #[test]
+    fn new_step_clears_redo() {
+        let mut state = load();
+        let mut history = History::new(10);
+        history.step(&mut state).unwrap();
+        history.step(&mut state).unwrap();
+        assert!(history.step_back(&mut state));
+
+        history.step(&mut state).unwrap();
+        assert!(!history.step_forward(&mut state));
+        assert_eq!(history.depth(), 2);
+    }
+
+    #[test]
+    fn capacity_evicts_oldest_step() {
+        let mut state = load();
+        let mut history = History::new(2);
+        for _ in 0..3 {
+            history.step(&mut state).unwrap();
+        }
+        assert_eq!(history.depth(), 2);
+
+        assert!(history.step_back(&mut state));
+        assert!(history.step_back(&mut state));
+        assert!(!history.step_back(&mut state));
+        assert_eq!(state.pc.pc, 2);
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut state = load();
+        let mut history = History::new(0);
+        history.step(&mut state).unwrap();
+        assert_eq!(history.depth(), 0);
+        assert!(!history.step_back(&mut state));
+        assert_eq!(state.pc.pc, 2);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::byte_formatter::byte_formatter::ByteFormatter;
+use crate::decoder::asm_decoder::{AsmProgram, ListingContent, SymbolKind};
+use crate::decoder::asm_source::FrameKind;
+use crate::encoder::bin_encoder::encode_command;
+use std::rc::Rc;
+
+// Листинг: адрес, слова команд или байты данных, номер строки и сама строка исходного текста.
+// Строки из раскрытия макросов помечаются `+` после номера, смена файла — комментарием с его именем.
+// Таблица символов: шестнадцатеричное значение, вид (code — метка команды, data — метка данных, const — константа) и имя.
+
+// байт данных в одной строке листинга
+const LISTING_DATA_BYTES: usize = 8;
+const CODE_COLUMN_WIDTH: usize = LISTING_DATA_BYTES * 3 - 1;
+
+pub fn render_listing(program: &AsmProgram, bytes_formatter: Rc<dyn ByteFormatter>) -> String {
+    let mut res = String::new();
+    let mut file = None;
+    for line in &program.listing {
+        let source = &line.source;
+        if file != Some(&source.file) {
+            res += &format!("// {}\n", source.file);
+            file = Some(&source.file);
+        }
+
+        let expanded = source.expansion.iter().any(|v| matches!(v.kind, FrameKind::Macro(_)));
+        let line_number = format!("{}{}", source.line_number, if expanded { "+" } else { "" });
+        let text = source.text.trim_end();
+
+        match &line.content {
+            ListingContent::Empty => {
+                res += &format!("{:6}{:CODE_COLUMN_WIDTH$}  {line_number:>5}  {text}\n", "", "");
+            }
+            ListingContent::Commands(address, commands) => {
+                let words = commands.iter()
+                    .map(|v| format!("{:04X}", bytes_formatter.wrap_bytes(encode_command(v, bytes_formatter.clone()))))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                res += &format!("{address:04X}: {words:CODE_COLUMN_WIDTH$}  {line_number:>5}  {text}\n");
+            }
+            ListingContent::Data(address, bytes) => {
+                let mut chunks = bytes.chunks(LISTING_DATA_BYTES);
+                let first = chunks.next().map(hex_bytes).unwrap_or_default();
+                res += &format!("{address:04X}: {first:CODE_COLUMN_WIDTH$}  {line_number:>5}  {text}\n");
+                for (index, chunk) in chunks.enumerate() {
+                    let chunk_address = address.wrapping_add(((index + 1) * LISTING_DATA_BYTES) as u16);
+                    res += &format!("{chunk_address:04X}: {}\n", hex_bytes(chunk));
+                }
+            }
+        }
+    }
+    res
+}
+
+pub fn render_map(program: &AsmProgram) -> String {
+    let mut symbols = program.symbols.iter().collect::<Vec<_>>();
+    symbols.sort_by_key(|(name, symbol)| (kind_order(symbol.kind), symbol.value, *name));
+
+    let mut res = String::new();
+    for (name, symbol) in symbols {
+        // отрицательные константы выводятся как `-0x…`, а не в дополнительном коде
+        let sign = if symbol.value < 0 { "-" } else { "" };
+        let value = format!("{sign}0x{:04X}", symbol.value.unsigned_abs());
+        let kind = match symbol.kind {
+            SymbolKind::CodeLabel => "code",
+            SymbolKind::DataLabel => "data",
+            SymbolKind::Constant => "const"
+        };
+        res += &format!("{value:<8}  {kind:<5}  {name}\n");
+    }
+    res
+}
+
+fn kind_order(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::CodeLabel => 0,
+        SymbolKind::DataLabel => 1,
+        SymbolKind::Constant => 2
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|v| format!("{v:02X}")).collect::<Vec<_>>().join(" ")
+}
\ No newline at end of file
@@ -9,130 +9,371 @@ mod program_counter;
 mod file_loaders;
 mod vm;
 mod encoder;
+mod cli_args;
+mod flags;
+mod vm_fault;
+mod trace;
+mod history;
+mod debugger;
+mod snapshot;
+mod object_file;
+mod listing;
 
 use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::cell::RefCell;
-use std::{env, fs, io};
-use crate::byte_formatter::byte_formatter::ByteFormatter;
+use std::{env, fs, io, process};
 use crate::common::default_error::DefaultError;
-use crate::vm::{init_vm, next_step, VmState};
+use crate::vm::{init_vm, next_step, run_to_end, stack_contents, CompareMode, OverflowPolicy, RunStop, VmConfig, VmConfigOverrides, VmState};
 use ratatui::crossterm::event;
-use ratatui::crossterm::event::{Event, KeyCode, KeyModifiers};
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
 use ratatui::{DefaultTerminal, Frame};
 use std::error::Error;
 use std::fmt::format;
-use std::io::{stdout, Write};
+use std::io::{stdout, BufWriter, Write};
 use std::ops::{Add, Deref};
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex, RwLock};
 use lazy_static::lazy_static;
 use ratatui::backend::Backend;
 use ratatui::prelude::{Color, Constraint, Direction, Layout, Line, Modifier, Rect, Span, Style};
 use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
-use crate::byte_formatter::little_endian_formatter::LittleEndianFormatter;
+use crate::byte_formatter::endianness::Endianness;
+use crate::cli_args::CliArgs;
+use crate::debugger::Debugger;
+use crate::object_file::{read_object, write_object, ObjectFile, Section};
+use crate::snapshot::{is_snapshot, load_snapshot, save_snapshot};
 use crate::command::{Command, CommandType};
 use crate::decoder::asm_decoder::parse_asm;
+use crate::decoder::asm_source::SourceLine;
+use crate::listing::{render_listing, render_map};
+use crate::encoder::asm_encoder::disassemble;
 use crate::encoder::bin_encoder::encode_command;
 use crate::file_loaders::load_string_file;
 use crate::memory::command_memory::CommandMemory;
 use crate::memory::data_memory::DataMemory;
 use crate::operand::Operand;
-use crate::register::{Register, RegisterName};
+use crate::register::RegisterName;
+use crate::trace::{TraceFormat, Tracer};
+
+const DEFAULT_STEP_BUDGET: u64 = 1_000_000;
+const DEFAULT_HISTORY_SIZE: usize = 1000;
+const DEFAULT_SNAPSHOT_FILE: &str = "vm.snapshot";
+const EXIT_FAULT: i32 = 2;
+const EXIT_STEP_BUDGET: i32 = 3;
+
+// параметры, которые принимает каждая команда
+const COMPILE_OPTIONS: &[&str] = &["endian", "data", "entry", "include-path", "listing", "map"];
+const DISASM_OPTIONS: &[&str] = &["endian"];
+const RUN_OPTIONS: &[&str] = &["endian", "cmp", "overflow", "steps", "dump", "trace", "trace-format"];
+const SNAPSHOT_SAVE_OPTIONS: &[&str] = &["endian", "cmp", "overflow", "steps"];
+const DEBUGGER_OPTIONS: &[&str] = &["endian", "cmp", "overflow", "break", "history", "snapshot-file"];
 
 fn main() -> Result<(), String> {
-    let bytes_formatter = Rc::new(LittleEndianFormatter::default());
-
-    let args: Vec<_> = env::args().collect();
-    // let args: Vec<_> = vec!["qwe", "/home/oop/MIREA/мага/1 курс/Разработка программно-аппаратного обеспечения информационных и автоматизированных систем/lab1/bin"];
-    // let args: Vec<_> = vec!["qwe", "compile", "/home/oop/MIREA/мага/1 курс/Разработка программно-аппаратного обеспечения информационных и автоматизированных систем/lab1/asm"];
-    if args.len() < 2 {
+    let cli_args = CliArgs::parse(env::args().skip(1))?;
+    let args = &cli_args.positional;
+    if args.is_empty() {
         return Err("invalid params".to_string());
     }
 
 
-    if args.len() > 1 && args[1] == "compile" {
-        let in_file = &args[2];
+    if args[0] == "compile" {
+        cli_args.check_options("compile", COMPILE_OPTIONS)?;
+        let in_file = args.get(1).ok_or("missing asm file")?;
+
+        let include_paths = cli_args.get_all("include-path").into_iter().map(PathBuf::from).collect::<Vec<_>>();
 
-        let asm = parse_asm(&load_string_file(in_file).default_res()?)?;
+        let lines = SourceLine::from_file(in_file, &load_string_file(in_file).default_res()?);
+        let asm = parse_asm(lines, &include_paths, cli_args.get_parsed::<Endianness>("endian")?).map_err(|diagnostics| {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic.render());
+            }
+            format!("could not compile `{in_file}` due to {} previous error(s)", diagnostics.len())
+        })?;
+        let bytes_formatter = asm.endianness.formatter();
+
+        if let Some(path) = cli_args.get("listing") {
+            fs::write(path, render_listing(&asm, bytes_formatter.clone())).map_err(|e| format!("could not write listing `{path}`: {e}"))?;
+        }
+        if let Some(path) = cli_args.get("map") {
+            fs::write(path, render_map(&asm)).map_err(|e| format!("could not write map `{path}`: {e}"))?;
+        }
 
-        stdout().write_all(&asm.into_iter()
+        let code = asm.commands.into_iter()
             .map(|v| encode_command(&v, bytes_formatter.clone()))
             .flat_map(|v| v)
-            .collect::<Vec<u8>>()).unwrap();
+            .collect::<Vec<u8>>();
+
+        // данные из директив ассемблера дополняют образ памяти, переданный через `--data`
+        let mut data_memory = match cli_args.get("data") {
+            Some(path) => DataMemory::restore(&fs::read(path).default_res()?)?,
+            None => DataMemory::default()
+        };
+        for (address, byte) in asm.data {
+            data_memory.put(address, byte);
+        }
+
+        let object = ObjectFile {
+            endianness: Some(asm.endianness),
+            // `--entry` заменяет директиву `.entry`
+            entry: cli_args.get("entry").map(parse_address).transpose()?.or(asm.entry).unwrap_or(0),
+            code: Section { address: 0, bytes: code },
+            data: data_memory.blocks().into_iter()
+                .map(|(address, bytes)| Section { address, bytes })
+                .collect()
+        };
+        stdout().write_all(&write_object(&object)).unwrap();
 
         return Ok(())
     }
 
+    if args[0] == "disasm" {
+        cli_args.check_options("disasm", DISASM_OPTIONS)?;
+        let in_file = args.get(1).ok_or("missing binary file")?;
 
-    let (command_path, memory_path, dump_path) = if args.len() > 1 && args[1] == "dump" {
-        (&args[2], None, args.get(3))
-    } else {
-        (&args[1], args.get(2), None)
-    };
+        let object = read_object(&fs::read(in_file).default_res()?)?;
+        let endianness = resolve_endianness(object.endianness, &cli_args)?;
+        let disassembly = disassemble(&object.code.bytes, object.code.address, endianness.formatter());
+        // директивы позволяют собрать из листинга тот же файл без дополнительных параметров
+        if let Some(endianness) = object.endianness {
+            println!(".endian {endianness}");
+        }
+        if object.entry != 0 {
+            println!(".entry {}", object.entry);
+        }
+        for line in disassembly.lines {
+            println!("{line}");
+        }
+        for section in &object.data {
+            println!(".org {}", section.address);
+            for chunk in section.bytes.chunks(16) {
+                println!(".byte {}", chunk.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "));
+            }
+        }
+
+        if disassembly.invalid_words > 0 {
+            return Err(format!("{} undecodable word(s), listing cannot be reassembled", disassembly.invalid_words));
+        }
+        return Ok(())
+    }
+
+    if args[0] == "run" {
+        cli_args.check_options("run", RUN_OPTIONS)?;
+        return run_headless(&cli_args);
+    }
+
+    if args[0] == "snapshot" && args.get(1).is_some_and(|v| v == "save") {
+        cli_args.check_options("snapshot save", SNAPSHOT_SAVE_OPTIONS)?;
+        let out_file = args.get(2).ok_or("missing snapshot file")?;
+        let command_path = args.get(3).ok_or("missing program file")?;
+        let max_steps = cli_args.get_parsed::<u64>("steps")?.unwrap_or(0);
+
+        let mut vm_state = load_vm(command_path, args.get(4), &cli_args)?;
+        let result = run_to_end(&mut vm_state, max_steps, next_step);
+        if let RunStop::Fault(fault) = result.stop {
+            eprintln!("{fault}");
+        }
+        return fs::write(out_file, save_snapshot(&vm_state)).default_res();
+    }
 
-    let command_memory = CommandMemory::load(fs::read(command_path).default_res()?, bytes_formatter.clone());
 
-    let memory_path = memory_path.map(|v| fs::read(v).unwrap());
-    let data_memory = memory_path.map(|v| DataMemory::restore(&v));
+    let (command_path, memory_path, dump_path) = if args[0] == "dump" {
+        (args.get(1).ok_or("missing program file")?, None, args.get(2))
+    } else if args[0] == "snapshot" {
+        match args.get(1).map(|v| v.as_str()) {
+            Some("load") => (args.get(2).ok_or("missing snapshot file")?, None, None),
+            Some(other) => return Err(format!("unknown snapshot subcommand `{other}`, expected `save` or `load`")),
+            None => return Err("missing snapshot subcommand, expected `save` or `load`".to_string())
+        }
+    } else {
+        (&args[0], args.get(1), None)
+    };
+    cli_args.check_options("debugger", DEBUGGER_OPTIONS)?;
 
-    let vm_state = init_vm(bytes_formatter, command_memory, data_memory)?;
+    let vm_state = load_vm(command_path, memory_path, &cli_args)?;
+    let breakpoints = cli_args.get_all("break").into_iter()
+        .map(parse_address)
+        .collect::<Result<BTreeSet<u16>, String>>()?;
 
 
     color_eyre::install().default_res()?;
     let terminal = ratatui::init();
-    let result = run(terminal, vm_state);
+    let history_size = cli_args.get_parsed::<usize>("history")?.unwrap_or(DEFAULT_HISTORY_SIZE);
+    let snapshot_path = cli_args.get("snapshot-file").unwrap_or(DEFAULT_SNAPSHOT_FILE).to_string();
+    let debugger = Debugger::new(vm_state, breakpoints, history_size, snapshot_path, config_overrides(&cli_args)?);
+    let result = run(terminal, debugger);
     ratatui::restore();
 
-    if result.is_ok() && dump_path.is_some() {
-        let dump = result?.data_memory.dump();
-        fs::File::create(dump_path.unwrap()).default_res()?.write(&dump).default_res()?;
-
-        Ok(())
+    if let Some(dump_path) = dump_path {
+        write_dump(&result?, dump_path)
     } else {
         result.map(|_| ())
     }
 }
 
-fn run(mut terminal: DefaultTerminal, mut vm: VmState) -> Result<VmState, String> {
-    let args: Vec<_> = env::args().collect();
+// явно заданные параметры командной строки перекрывают базовые (например, сохранённые в снимке)
+fn config_overrides(cli_args: &CliArgs) -> Result<VmConfigOverrides, String> {
+    Ok(VmConfigOverrides {
+        compare_mode: cli_args.get_parsed::<CompareMode>("cmp")?,
+        overflow_policy: cli_args.get_parsed::<OverflowPolicy>("overflow")?
+    })
+}
+
+fn vm_config(cli_args: &CliArgs, base: VmConfig) -> Result<VmConfig, String> {
+    Ok(config_overrides(cli_args)?.apply(base))
+}
+
+// порядок байт, записанный в файле, должен совпадать с явно заданным `--endian`
+fn resolve_endianness(stored: Option<Endianness>, cli_args: &CliArgs) -> Result<Endianness, String> {
+    let requested = cli_args.get_parsed::<Endianness>("endian")?;
+    match (stored, requested) {
+        (Some(stored), Some(requested)) if stored != requested =>
+            Err(format!("program is {stored}-endian, but `--endian {requested}` was requested")),
+        (stored, requested) => Ok(stored.or(requested).unwrap_or_default())
+    }
+}
+
+fn load_vm(command_path: &str, memory_path: Option<&String>, cli_args: &CliArgs) -> Result<VmState, String> {
+    let bytes = fs::read(command_path).default_res()?;
+    if is_snapshot(&bytes) {
+        if memory_path.is_some() {
+            return Err("memory file cannot be combined with a snapshot".to_string());
+        }
+        let mut vm_state = load_snapshot(&bytes)?;
+        resolve_endianness(Some(vm_state.bytes_formatter.endianness()), cli_args)?;
+        vm_state.config = vm_config(cli_args, vm_state.config)?;
+        return Ok(vm_state);
+    }
+
+    let object = read_object(&bytes)?;
+    let bytes_formatter = resolve_endianness(object.endianness, cli_args)?.formatter();
+    let config = vm_config(cli_args, VmConfig::default())?;
+    let command_memory = CommandMemory::load(object.code.bytes.clone(), object.code.address, bytes_formatter.clone());
+
+    // явно заданный файл памяти заменяет данные, записанные в программе
+    let data_memory = match memory_path {
+        Some(path) => DataMemory::restore(&fs::read(path).default_res()?)?,
+        None => object.data_memory()
+    };
+
+    let mut vm_state = init_vm(bytes_formatter, command_memory, Some(data_memory), config)?;
+    vm_state.pc.pc = object.entry;
+    Ok(vm_state)
+}
+
+fn parse_address(value: &str) -> Result<u16, String> {
+    let parsed = if let Some(hex) = value.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16)
+    } else {
+        value.parse::<u16>()
+    };
+    parsed.map_err(|_| format!("invalid address: {value}"))
+}
+
+fn write_dump(vm_state: &VmState, dump_path: &str) -> Result<(), String> {
+    let dump = vm_state.data_memory.dump();
+    fs::File::create(dump_path).default_res()?.write_all(&dump).default_res()
+}
+
+fn run_headless(cli_args: &CliArgs) -> Result<(), String> {
+    let command_path = cli_args.positional.get(1).ok_or("missing program file")?;
+    let max_steps = cli_args.get_parsed::<u64>("steps")?.unwrap_or(DEFAULT_STEP_BUDGET);
+
+    let mut vm_state = load_vm(command_path, cli_args.positional.get(2), cli_args)?;
+    let result = if let Some(trace_path) = cli_args.get("trace") {
+        let trace_format = cli_args.get_parsed::<TraceFormat>("trace-format")?.unwrap_or_default();
+        let trace_file = BufWriter::new(fs::File::create(trace_path).default_res()?);
+
+        let mut tracer = Tracer::new(Box::new(trace_file), trace_format);
+        let result = run_to_end(&mut vm_state, max_steps, |state| tracer.step(state));
+        tracer.finish().default_res()?;
+        result
+    } else {
+        run_to_end(&mut vm_state, max_steps, next_step)
+    };
+
+    println!("{:3}: {:04X} ({})", "PC", vm_state.pc.pc, vm_state.pc.pc);
+    for (name, value) in vm_state.registers.get_values() {
+        let register_name: &str = name.into();
+        println!("{:3}: {:04X} ({})", register_name, value, value);
+    }
+    println!("Flags: {}", vm_state.flags);
+    println!("steps: {}", result.steps);
+
+    if let Some(dump_path) = cli_args.get("dump") {
+        write_dump(&vm_state, dump_path)?;
+    }
+
+    match result.stop {
+        RunStop::Halted => Ok(()),
+        RunStop::RanOffEnd => {
+            eprintln!("program ran off the end of loaded code");
+            Ok(())
+        }
+        RunStop::StepBudget => {
+            eprintln!("step budget of {max_steps} exhausted");
+            process::exit(EXIT_STEP_BUDGET)
+        }
+        RunStop::Fault(fault) => {
+            eprintln!("{fault}");
+            process::exit(EXIT_FAULT)
+        }
+    }
+}
 
+fn run(mut terminal: DefaultTerminal, mut debugger: Debugger) -> Result<VmState, String> {
     loop {
-        terminal.draw(|f| render(f, &vm)).default_res()?;
-        match event::read().default_res()? {
-            Event::Key(key_event) => {
-                if key_event.code == KeyCode::Char(' ') {
-                    if !next_step(&mut vm)? {
-                        // break Ok(())
-                    }
-                }
-                if key_event.code == KeyCode::Char('c') && key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                    break Ok(vm)
-                }
+        terminal.draw(|f| render(f, &debugger)).default_res()?;
+        if let Event::Key(key_event) = event::read().default_res()? {
+            if key_event.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key_event.code {
+                KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => break Ok(debugger.vm),
+                KeyCode::Char(' ') => debugger.step(),
+                KeyCode::Char('c') => debugger.continue_run(DEFAULT_STEP_BUDGET),
+                KeyCode::Char('b') => debugger.toggle_breakpoint(),
+                KeyCode::Char('s') => debugger.save_snapshot(),
+                KeyCode::Char('l') => debugger.load_snapshot(),
+                KeyCode::Left => debugger.step_back(),
+                KeyCode::Right => debugger.step_forward(),
+                KeyCode::Up => debugger.move_cursor(-1),
+                KeyCode::Down => debugger.move_cursor(1),
+                _ => {}
             }
-            _ => {}
         }
     }
 }
 
-fn render(frame: &mut Frame, vm_state: &VmState) {
-    ui(frame, vm_state)
+fn render(frame: &mut Frame, debugger: &Debugger) {
+    ui(frame, debugger)
     // frame.render_widget("hello world", frame.area());
 }
 
 
-fn ui(f: &mut Frame, vm_state: &VmState) {
+fn ui(f: &mut Frame, debugger: &Debugger) {
+    let vm_state = &debugger.vm;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([
-            Constraint::Length(3),  // Заголовок
+            Constraint::Length(4),  // Заголовок
             Constraint::Min(10),    // Основная область
             // Constraint::Length(3),  // Статус/ввод
         ])
         .split(f.size());
 
     // Заголовок
-    let header = Paragraph::new("Press space to continue")
+    let status = if vm_state.halted {
+        Span::styled("HALTED ", Style::default().fg(Color::Red))
+    } else {
+        Span::raw("")
+    };
+    let header = Paragraph::new(vec![
+        Line::from("[space] step  [left/right] step back/forward  [c] continue  [b] toggle breakpoint  [s/l] save/load snapshot  [up/down] select line  [ctrl+c] exit"),
+        Line::from(vec![status, Span::raw(debugger.message.clone().unwrap_or_default())]),
+    ])
         .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(header, chunks[0]);
@@ -148,20 +389,19 @@ fn ui(f: &mut Frame, vm_state: &VmState) {
         .split(chunks[1]);
 
     // Код программы
-    render_code(f, vm_state, main_chunks[0]);
+    render_code(f, debugger, main_chunks[0]);
 
     // Регистры и стек
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(100), // Регистры
-            // Constraint::Percentage(50), // Стек
+            Constraint::Percentage(50), // Регистры
+            Constraint::Percentage(50), // Стек
         ])
         .split(main_chunks[1]);
 
-    // render_registers(f, vm_state, right_chunks[0]);
-    render_registers(f, vm_state, main_chunks[1]);
-    // render_stack(f, vm_state, right_chunks[1]);
+    render_registers(f, vm_state, right_chunks[0]);
+    render_stack(f, vm_state, right_chunks[1]);
 
     // Память и вывод
     let output_chunks = Layout::default()
@@ -185,17 +425,19 @@ impl From<Command> for Cow<'_, str> {
         let command_name: &str = value.command_type.into();
         let operand_str: &str = match value.operand {
             Operand::Literal(v) => &v.to_string(),
+            Operand::SignedLiteral(v) => &v.to_string(),
             Operand::Register(r) => r.into()
         };
         Cow::from(format!("{} {}", command_name, operand_str))
     }
 }
 
-fn render_code(f: &mut Frame, vm_state: &VmState, area: Rect) {
+fn render_code(f: &mut Frame, debugger: &Debugger, area: Rect) {
+    let vm_state = &debugger.vm;
     let mut sorted_commands = vm_state.command_memory.get_all();
     sorted_commands.sort_by_key(|v| v.0);
 
-    let selected = sorted_commands.iter().enumerate().find(|v| v.1.0 == vm_state.pc.pc).map(|v| v.0);
+    let selected = sorted_commands.iter().enumerate().find(|v| v.1.0 == debugger.cursor).map(|v| v.0);
 
 
     let code_items: Vec<ListItem> = sorted_commands
@@ -207,8 +449,11 @@ fn render_code(f: &mut Frame, vm_state: &VmState, area: Rect) {
                 style = style.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
             }
 
+            let breakpoint_mark = if debugger.breakpoints.contains(&address) { "● " } else { "  " };
+
             let formatter = vm_state.bytes_formatter.clone();
             ListItem::new(Line::from(vec![
+                Span::styled(breakpoint_mark, Style::default().fg(Color::Red)),
                 Span::styled(format!("{:04X}; ", address), Style::default().fg(Color::DarkGray)),
                 Span::styled(format!("{:04}: ", address), Style::default().fg(Color::DarkGray)),
                 Span::styled(format!("{:04X}; ", formatter.clone().wrap_bytes(encode_command(&command, formatter))), style),
@@ -225,7 +470,8 @@ fn render_code(f: &mut Frame, vm_state: &VmState, area: Rect) {
                 .borders(Borders::ALL)
                 .style(Style::default().fg(Color::White)),
         )
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
 
     let mut list_state = ListState::default().with_selected(selected);
 
@@ -245,6 +491,10 @@ fn render_registers(f: &mut Frame, vm_state: &VmState, area: Rect) {
             Span::styled(format!("{:04X} ({})", value.as_u16(), value.as_u16()), Style::default().fg(Color::Green)),
         ]));
     }
+    registers_text.push(Line::from(vec![
+        Span::styled("Flags: ", Style::default().fg(Color::Yellow)),
+        Span::styled(vm_state.flags.to_string(), Style::default().fg(Color::Green)),
+    ]));
 
     let registers = Paragraph::new(registers_text)
         .block(Block::default().title(" Registers ").borders(Borders::ALL))
@@ -254,18 +504,22 @@ fn render_registers(f: &mut Frame, vm_state: &VmState, area: Rect) {
 }
 
 fn render_stack(f: &mut Frame, vm_state: &VmState, area: Rect) {
-    // let stack_items: Vec<ListItem> = app
-    //     .stack
-    //     .iter()
-    //     .rev()
-    //     .enumerate()
-    //     .map(|(i, val)| {
-    //         ListItem::new(format!("[{:02}]: {:08X} ({})",
-    //                               app.stack.len() - i - 1, val, val))
-    //     })
-    //     .collect();
+    let stack = stack_contents(vm_state);
+    let sp = vm_state.registers.get(RegisterName::Sp).unwrap().as_u16();
+
+    let stack_items: Vec<ListItem> = stack
+        .iter()
+        .enumerate()
+        .map(|(i, val)| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:04X}; ", sp.wrapping_add(i as u16 * 2)), Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("[{:02}]: ", stack.len() - i - 1), Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{:04X} ({})", val, val), Style::default().fg(Color::Green)),
+            ]))
+        })
+        .collect();
 
-    let stack_list = List::new(vec![] as Vec<ListItem>)
+    let stack_list = List::new(stack_items)
         .block(Block::default().title(" Stack ").borders(Borders::ALL))
         .style(Style::default().fg(Color::White));
 
@@ -275,6 +529,7 @@ fn render_stack(f: &mut Frame, vm_state: &VmState, area: Rect) {
 fn get_value(operand: &Operand, vm_state: &VmState) -> u16 {
     match operand {
         Operand::Literal(v) => *v,
+        Operand::SignedLiteral(v) => *v as i16 as u16,
         Operand::Register(register_name) => {
             vm_state.registers.get(*register_name).unwrap().as_u16()
         }
@@ -2,7 +2,7 @@ use crate::command::Command;
 use std::collections::HashMap;
 use std::rc::Rc;
 use crate::byte_formatter::byte_formatter::ByteFormatter;
-use crate::decoder::bin_decoder::parse_command;
+use crate::decoder::bin_decoder::{parse_command, DecodeError};
 
 pub struct CommandMemory {
     data: HashMap<u16, u8>,
@@ -11,10 +11,10 @@ pub struct CommandMemory {
 
 impl CommandMemory {
 
-    pub fn load(commands: Vec<u8>, bytes_formatter: Rc<dyn ByteFormatter>) -> CommandMemory {
+    pub fn load(commands: Vec<u8>, load_address: u16, bytes_formatter: Rc<dyn ByteFormatter>) -> CommandMemory {
 
         let command_map = commands.into_iter().enumerate()
-            .map(|(address, cmd)| (address as u16, cmd))
+            .map(|(offset, cmd)| (load_address.wrapping_add(offset as u16), cmd))
             .collect();
 
         CommandMemory {
@@ -23,15 +23,43 @@ impl CommandMemory {
         }
     }
 
-    pub fn get(&self, address: u16) -> Result<Option<Command>, String> {
-        let first_byte = self.data.get(&address).unwrap_or(&0);
-        let second_byte = self.data.get(&(address + 1)).unwrap_or(&0);
+    pub fn from_entries(entries: Vec<(u16, u8)>, bytes_formatter: Rc<dyn ByteFormatter>) -> CommandMemory {
+        CommandMemory {
+            data: entries.into_iter().collect(),
+            bytes_formatter
+        }
+    }
+
+    pub fn entries(&self) -> Vec<(u16, u8)> {
+        let mut entries = self.data.iter().map(|(address, byte)| (*address, *byte)).collect::<Vec<_>>();
+        entries.sort();
+        entries
+    }
+
+    pub fn get(&self, address: u16) -> Result<Option<Command>, DecodeError> {
+        let Some(bytes) = self.get_bytes(address) else {
+            return Ok(None);
+        };
 
-        let cmd = parse_command(&[*first_byte, *second_byte], self.bytes_formatter.clone())?;
+        let cmd = parse_command(&bytes, self.bytes_formatter.clone())?;
 
         Ok(Some(cmd))
     }
 
+    pub fn get_word(&self, address: u16) -> Option<u16> {
+        self.get_bytes(address).map(|bytes| self.bytes_formatter.wrap_bytes(bytes))
+    }
+
+    fn get_bytes(&self, address: u16) -> Option<[u8; 2]> {
+        let first_byte = self.data.get(&address);
+        let second_byte = self.data.get(&address.wrapping_add(1));
+        if first_byte.is_none() && second_byte.is_none() {
+            return None;
+        }
+
+        Some([*first_byte.unwrap_or(&0), *second_byte.unwrap_or(&0)])
+    }
+
     pub fn get_all(&self) -> Vec<(u16, Command)> {
         let indexes = self.data.keys();
 
@@ -1,29 +1,108 @@
-use std::cmp::{max, min};
+use crate::common::byte_reader::ByteReader;
 use std::collections::HashMap;
 
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryWrite {
+    pub address: u16,
+    pub old: u8,
+    pub new: u8
+}
+
+// Формат дампа: "VMMD", версия, число блоков (u32) и блоки ненулевых байт
+// в виде (начальный адрес u16, длина u32, байты); многобайтовые поля в little endian.
+// Файлы без заголовка считаются дампами старого формата — образом памяти начиная с адреса 0.
+const DUMP_MAGIC: &[u8; 4] = b"VMMD";
+const DUMP_VERSION: u8 = 1;
+const DUMP_BLOCK_HEADER_SIZE: usize = 6;
+const MEMORY_SIZE: usize = 1 << 16;
+
 #[derive(Default)]
 pub struct DataMemory {
-    data: HashMap<u16, u8>
+    data: HashMap<u16, u8>,
+    journal: Option<Vec<MemoryWrite>>
 }
 
 impl DataMemory {
 
-    pub fn dump(&self) -> [u8; 1 << 15] {
-        let mut res = [0u8; 1 << 15];
-        for i in 0u16..(1 << 15) {
-            res[i as usize] = *self.data.get(&i).unwrap_or(&0u8);
+    pub fn dump(&self) -> Vec<u8> {
+        let blocks = self.blocks();
+
+        let mut res = Vec::new();
+        res.extend_from_slice(DUMP_MAGIC);
+        res.push(DUMP_VERSION);
+        res.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+        for (start, bytes) in blocks {
+            res.extend_from_slice(&start.to_le_bytes());
+            res.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            res.extend_from_slice(&bytes);
         }
 
         res
     }
 
-    pub fn restore(dump: &[u8]) -> Self {
+    pub fn restore(dump: &[u8]) -> Result<Self, String> {
+        let Some(body) = dump.strip_prefix(DUMP_MAGIC) else {
+            return Ok(Self::restore_raw(dump));
+        };
+
+        let mut reader = ByteReader::new(body);
+        let version = reader.read_u8()?;
+        if version != DUMP_VERSION {
+            return Err(format!("unsupported memory dump version {version}"));
+        }
+
         let mut data = HashMap::new();
-        for i in 0u16..min(u16::MAX as usize, dump.len()) as u16 {
-            data.insert(i, dump[i as usize]);
+        for _ in 0..reader.read_u32()? {
+            let start = reader.read_u16()?;
+            let len = reader.read_u32()? as usize;
+            if start as usize + len > MEMORY_SIZE {
+                return Err(format!("memory dump block at {start:04X} exceeds address space"));
+            }
+            for (offset, byte) in reader.read_bytes(len)?.iter().enumerate() {
+                data.insert(start + offset as u16, *byte);
+            }
+        }
+
+        Ok(Self { data, journal: None })
+    }
+
+    fn restore_raw(dump: &[u8]) -> Self {
+        let data = dump.iter()
+            .take(MEMORY_SIZE)
+            .enumerate()
+            .map(|(address, byte)| (address as u16, *byte))
+            .collect();
+
+        Self { data, journal: None }
+    }
+
+    pub fn from_entries(entries: Vec<(u16, u8)>) -> Self {
+        Self { data: entries.into_iter().collect(), journal: None }
+    }
+
+    // блоки ненулевых байт в порядке возрастания адресов; промежутки из нулей,
+    // не превышающие размер заголовка блока, включаются в блок
+    pub fn blocks(&self) -> Vec<(u16, Vec<u8>)> {
+        let mut blocks: Vec<(u16, Vec<u8>)> = Vec::new();
+        for (address, byte) in self.entries() {
+            match blocks.last_mut() {
+                Some((start, bytes)) if address as usize - (*start as usize + bytes.len()) <= DUMP_BLOCK_HEADER_SIZE => {
+                    bytes.resize(address as usize - *start as usize, 0);
+                    bytes.push(byte);
+                }
+                _ => blocks.push((address, vec![byte]))
+            }
         }
+        blocks
+    }
 
-        Self { data }
+    pub fn entries(&self) -> Vec<(u16, u8)> {
+        let mut entries = self.data.iter()
+            .filter(|(_, byte)| **byte != 0)
+            .map(|(address, byte)| (*address, *byte))
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries
     }
 }
 
@@ -34,13 +113,72 @@ impl DataMemory {
     }
 
     pub fn put(&mut self, address: u16, value: u8) {
-        self.data.insert(address, value);
+        let old = self.data.insert(address, value).unwrap_or(0);
+        if let Some(journal) = &mut self.journal {
+            journal.push(MemoryWrite { address, old, new: value });
+        }
     }
 
     pub fn put_bytes(&mut self, address: u16, value: &[u8]) {
-        let max_address = address + value.len() as u16;
-        for index in address..max_address {
-            self.data.insert(index, value[(index - address) as usize]);
+        for (offset, byte) in value.iter().enumerate() {
+            self.put(address.wrapping_add(offset as u16), *byte);
         }
     }
+
+    // включает запись всех изменений памяти, накопленные записи забираются take_writes
+    pub fn record_writes(&mut self) {
+        self.journal.get_or_insert_with(Vec::new);
+    }
+
+    pub fn take_writes(&mut self) -> Vec<MemoryWrite> {
+        self.journal.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_round_trip() {
+        let mut memory = DataMemory::default();
+        memory.put_bytes(0, &[1, 2, 3]);
+        memory.put(0x10, 4);
+        memory.put(0x8000, 5);
+        memory.put(0xffff, 6);
+
+        let restored = DataMemory::restore(&memory.dump()).unwrap();
+        assert_eq!(restored.entries(), memory.entries());
+        assert_eq!(restored.get(0xffff), 6);
+    }
+
+    #[test]
+    fn small_zero_gaps_are_merged_into_blocks() {
+        let memory = DataMemory::from_entries(vec![(0, 1), (7, 2), (100, 3)]);
+        assert_eq!(memory.blocks(), [(0, vec![1, 0, 0, 0, 0, 0, 0, 2]), (100, vec![3])]);
+    }
+
+    #[test]
+    fn legacy_raw_dump() {
+        let mut raw = vec![0u8; 32 * 1024];
+        raw[0] = 7;
+        raw[2] = 0x34;
+        raw[3] = 0x12;
+        raw[32 * 1024 - 1] = 9;
+
+        let memory = DataMemory::restore(&raw).unwrap();
+        assert_eq!(memory.entries(), [(0, 7), (2, 0x34), (3, 0x12), (0x7fff, 9)]);
+        assert_eq!(memory.get(0x8000), 0);
+    }
+
+    #[test]
+    fn rejects_invalid_dumps() {
+        let mut dump = DataMemory::from_entries(vec![(0xfffe, 1)]).dump();
+        // длина блока выходит за пределы адресного пространства
+        dump[11] = 3;
+        assert!(DataMemory::restore(&dump).is_err());
+
+        assert!(DataMemory::restore(b"VMMD\x02").is_err());
+        assert!(DataMemory::restore(b"VMMD\x01\x01\x00").is_err());
+    }
 }
\ No newline at end of file
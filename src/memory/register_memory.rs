@@ -31,4 +31,10 @@ impl RegisterMemory {
     pub fn get_all(&self) -> &HashMap<RegisterName, Register> {
         &self.data
     }
+
+    pub fn get_values(&self) -> Vec<(RegisterName, u16)> {
+        let mut values = self.data.iter().map(|(name, register)| (*name, register.as_u16())).collect::<Vec<_>>();
+        values.sort_by_key(|(name, _)| name.get_id());
+        values
+    }
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+use crate::byte_formatter::endianness::Endianness;
+use crate::common::byte_reader::ByteReader;
+use crate::memory::data_memory::DataMemory;
+use strum_macros::FromRepr;
+
+// Формат скомпилированной программы (многобайтовые поля в little endian):
+// "VMOB", версия, порядок байт (0 — little, 1 — big), точка входа (u16), число секций (u16)
+// и секции в виде (тип, адрес загрузки u16, длина u32, байты).
+// Файлы без заголовка считаются программами старого формата — кодом, загружаемым с адреса 0,
+// порядок байт для них задаётся параметром `--endian`.
+const OBJECT_MAGIC: &[u8; 4] = b"VMOB";
+const OBJECT_VERSION: u8 = 2;
+const ADDRESS_SPACE_SIZE: usize = 1 << 16;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, FromRepr)]
+#[repr(u8)]
+pub enum SectionKind {
+    Code,
+    Data
+}
+
+pub struct Section {
+    pub address: u16,
+    pub bytes: Vec<u8>
+}
+
+pub struct ObjectFile {
+    pub endianness: Option<Endianness>,
+    pub entry: u16,
+    pub code: Section,
+    pub data: Vec<Section>
+}
+
+impl ObjectFile {
+
+    pub fn data_memory(&self) -> DataMemory {
+        DataMemory::from_entries(self.data.iter()
+            .flat_map(|section| section.bytes.iter().enumerate()
+                .map(|(offset, byte)| (section.address.wrapping_add(offset as u16), *byte)))
+            .collect())
+    }
+}
+
+pub fn write_object(object: &ObjectFile) -> Vec<u8> {
+    let sections = std::iter::once((SectionKind::Code, &object.code))
+        .chain(object.data.iter().map(|section| (SectionKind::Data, section)))
+        .collect::<Vec<_>>();
+
+    let mut res = Vec::new();
+    res.extend_from_slice(OBJECT_MAGIC);
+    res.push(OBJECT_VERSION);
+    res.push(object.endianness.unwrap_or_default() as u8);
+    res.extend_from_slice(&object.entry.to_le_bytes());
+    res.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+    for (kind, section) in sections {
+        res.push(kind as u8);
+        res.extend_from_slice(&section.address.to_le_bytes());
+        res.extend_from_slice(&(section.bytes.len() as u32).to_le_bytes());
+        res.extend_from_slice(&section.bytes);
+    }
+    res
+}
+
+pub fn read_object(bytes: &[u8]) -> Result<ObjectFile, String> {
+    let Some(body) = bytes.strip_prefix(OBJECT_MAGIC) else {
+        return Ok(ObjectFile {
+            endianness: None,
+            entry: 0,
+            code: Section { address: 0, bytes: bytes.to_vec() },
+            data: Vec::new()
+        });
+    };
+
+    let mut reader = ByteReader::new(body);
+    let version = reader.read_u8()?;
+    if version != OBJECT_VERSION {
+        return Err(format!("unsupported object file version {version}"));
+    }
+    let endianness = reader.read_u8()?;
+    let endianness = Endianness::from_repr(endianness).ok_or(format!("invalid endianness {endianness} in object file"))?;
+
+    let entry = reader.read_u16()?;
+    let mut code = None;
+    let mut data = Vec::new();
+    for _ in 0..reader.read_u16()? {
+        let kind = reader.read_u8()?;
+        let address = reader.read_u16()?;
+        let len = reader.read_u32()? as usize;
+        if address as usize + len > ADDRESS_SPACE_SIZE {
+            return Err(format!("section at {address:04X} exceeds address space"));
+        }
+        let section = Section { address, bytes: reader.read_bytes(len)?.to_vec() };
+
+        match SectionKind::from_repr(kind) {
+            Some(SectionKind::Code) if code.is_some() => return Err("object file has more than one code section".to_string()),
+            Some(SectionKind::Code) => code = Some(section),
+            Some(SectionKind::Data) => data.push(section),
+            None => return Err(format!("invalid section type {kind} in object file"))
+        }
+    }
+
+    Ok(ObjectFile {
+        endianness: Some(endianness),
+        entry,
+        code: code.unwrap_or(Section { address: 0, bytes: Vec::new() }),
+        data
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_round_trip() {
+        let object = ObjectFile {
+            endianness: Some(Endianness::Big),
+            entry: 4,
+            code: Section { address: 0, bytes: vec![0x1d, 0x01, 0x91, 0x00] },
+            data: vec![
+                Section { address: 0x64, bytes: vec![1, 2, 3] },
+                Section { address: 0xfffe, bytes: vec![4, 5] },
+            ]
+        };
+
+        let read = read_object(&write_object(&object)).unwrap();
+        assert_eq!(read.endianness, Some(Endianness::Big));
+        assert_eq!(read.entry, 4);
+        assert_eq!(read.code.bytes, object.code.bytes);
+        assert_eq!(read.data.iter().map(|v| (v.address, v.bytes.clone())).collect::<Vec<_>>(), [(0x64, vec![1, 2, 3]), (0xfffe, vec![4, 5])]);
+        assert_eq!(read.data_memory().entries(), [(0x64, 1), (0x65, 2), (0x66, 3), (0xfffe, 4), (0xffff, 5)]);
+    }
+
+    #[test]
+    fn headerless_program() {
+        // файл без заголовка — код с адреса 0, порядок байт не известен
+        let read = read_object(&[0x1d, 0x01]).unwrap();
+        assert_eq!(read.endianness, None);
+        assert_eq!(read.code.bytes, [0x1d, 0x01]);
+    }
+
+    #[test]
+    fn rejects_invalid_objects() {
+        assert!(read_object(b"VMOB\x01\x01\x1d\x01").is_err());
+        assert!(read_object(b"VMOB\x03\x00").is_err());
+        assert!(read_object(b"VMOB\x02\x07\x00\x00\x00\x00").is_err());
+        // секция неизвестного типа
+        assert!(read_object(b"VMOB\x02\x00\x00\x00\x01\x00\x05\x00\x00\x00\x00\x00\x00").is_err());
+        // секция не помещается в адресное пространство
+        assert!(read_object(b"VMOB\x02\x00\x00\x00\x01\x00\x01\xff\xff\x02\x00\x00\x00\x01\x02").is_err());
+    }
+}
\ No newline at end of file
@@ -5,14 +5,17 @@ use enum_tags_traits::TaggedEnum;
 #[derive(Debug, Copy, Clone, Tag)]
 pub enum Operand {
     Literal(u16),
-    Register(RegisterName)
+    Register(RegisterName),
+    // 8-битный литерал, расширяемый знаком до 16 бит
+    SignedLiteral(i8)
 }
 
 impl OperandTag {
      pub fn get_id(&self) -> u16 {
          match self {
              OperandTag::Literal => 1,
-             OperandTag::Register => 2
+             OperandTag::Register => 2,
+             OperandTag::SignedLiteral => 3
          }
     }
 }
\ No newline at end of file
@@ -33,6 +33,7 @@ pub enum RegisterName {
     Rg3,
     Rg4,
     Rg5,
+    Sp,
 }
 
 impl RegisterName {
@@ -43,7 +44,8 @@ impl RegisterName {
             RegisterName::Rg2 => 3,
             RegisterName::Rg3 => 4,
             RegisterName::Rg4 => 5,
-            RegisterName::Rg5 => 6
+            RegisterName::Rg5 => 6,
+            RegisterName::Sp => 7
         }
     }
 
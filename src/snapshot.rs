@@ -0,0 +1,170 @@
+use crate::byte_formatter::endianness::Endianness;
+use crate::common::byte_reader::ByteReader;
+use crate::flags::Flags;
+use crate::memory::command_memory::CommandMemory;
+use crate::memory::data_memory::DataMemory;
+use crate::register::RegisterName;
+use crate::vm::{init_vm, CompareMode, OverflowPolicy, VmConfig, VmState};
+use std::collections::HashMap;
+use strum::IntoEnumIterator;
+
+// Формат снимка (многобайтовые поля в little endian):
+// "VMSS", версия, порядок байт, PC, флаги, признак останова, режим CMP, политика переполнения,
+// число регистров и пары (id, значение), затем память команд и память данных
+// в виде числа записей и пар (адрес, байт).
+const SNAPSHOT_MAGIC: &[u8; 4] = b"VMSS";
+const SNAPSHOT_VERSION: u8 = 2;
+
+pub fn is_snapshot(bytes: &[u8]) -> bool {
+    bytes.starts_with(SNAPSHOT_MAGIC)
+}
+
+pub fn save_snapshot(state: &VmState) -> Vec<u8> {
+    let mut res = Vec::new();
+    res.extend_from_slice(SNAPSHOT_MAGIC);
+    res.push(SNAPSHOT_VERSION);
+    res.push(state.bytes_formatter.endianness() as u8);
+    res.extend_from_slice(&state.pc.pc.to_le_bytes());
+    res.push(state.flags.to_bits());
+    res.push(state.halted as u8);
+    res.push(state.config.compare_mode as u8);
+    res.push(state.config.overflow_policy as u8);
+
+    let registers = state.registers.get_values();
+    res.push(registers.len() as u8);
+    for (name, value) in registers {
+        res.push(name.get_id() as u8);
+        res.extend_from_slice(&value.to_le_bytes());
+    }
+
+    write_entries(&mut res, &state.command_memory.entries());
+    write_entries(&mut res, &state.data_memory.entries());
+
+    res
+}
+
+pub fn load_snapshot(bytes: &[u8]) -> Result<VmState, String> {
+    if !is_snapshot(bytes) {
+        return Err("not a snapshot file".to_string());
+    }
+
+    let mut reader = ByteReader::new(&bytes[SNAPSHOT_MAGIC.len()..]);
+    let version = reader.read_u8()?;
+    if version != SNAPSHOT_VERSION {
+        return Err(format!("unsupported snapshot version {version}"));
+    }
+    let endianness = reader.read_u8()?;
+    let endianness = Endianness::from_repr(endianness).ok_or(format!("invalid endianness {endianness} in snapshot"))?;
+    let bytes_formatter = endianness.formatter();
+
+    let pc = reader.read_u16()?;
+    let flags = Flags::from_bits(reader.read_u8()?);
+    let halted = reader.read_u8()? != 0;
+    let config = VmConfig {
+        compare_mode: CompareMode::from_repr(reader.read_u8()?).ok_or("invalid compare mode in snapshot")?,
+        overflow_policy: OverflowPolicy::from_repr(reader.read_u8()?).ok_or("invalid overflow policy in snapshot")?
+    };
+
+    let register_names = RegisterName::iter().map(|v| (v.get_id(), v)).collect::<HashMap<_, _>>();
+    let mut registers = Vec::new();
+    for _ in 0..reader.read_u8()? {
+        let id = reader.read_u8()? as u16;
+        let name = *register_names.get(&id).ok_or(format!("invalid register id {id} in snapshot"))?;
+        registers.push((name, reader.read_u16()?));
+    }
+
+    let command_memory = CommandMemory::from_entries(read_entries(&mut reader)?, bytes_formatter.clone());
+    let data_memory = DataMemory::from_entries(read_entries(&mut reader)?);
+
+    let mut state = init_vm(bytes_formatter, command_memory, Some(data_memory), config)?;
+    for (name, value) in registers {
+        state.registers.get_mut(name).unwrap().put_u16(value);
+    }
+    state.pc.pc = pc;
+    state.flags = flags;
+    state.halted = halted;
+
+    Ok(state)
+}
+
+fn write_entries(res: &mut Vec<u8>, entries: &[(u16, u8)]) {
+    res.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for (address, byte) in entries {
+        res.extend_from_slice(&address.to_le_bytes());
+        res.push(*byte);
+    }
+}
+
+fn read_entries(reader: &mut ByteReader) -> Result<Vec<(u16, u8)>, String> {
+    let count = reader.read_u32()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        entries.push((reader.read_u16()?, reader.read_u8()?));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{next_step, StepOutcome};
+
+    // mov 5; lrg rg1; dac 3; halt
+    const PROGRAM: [u16; 4] = [0x1d05, 0x2202, 0x1103, 0x9100];
+
+    fn machine(endianness: Endianness) -> VmState {
+        let bytes_formatter = endianness.formatter();
+        let code = PROGRAM.iter().flat_map(|v| bytes_formatter.unwrap_bytes(*v)).collect();
+        let command_memory = CommandMemory::load(code, 0, bytes_formatter.clone());
+        let config = VmConfig {
+            compare_mode: CompareMode::Legacy,
+            overflow_policy: OverflowPolicy::Saturate
+        };
+        init_vm(bytes_formatter, command_memory, None, config).unwrap()
+    }
+
+    fn assert_same_state(a: &VmState, b: &VmState) {
+        assert_eq!(a.pc.pc, b.pc.pc);
+        assert_eq!(a.flags, b.flags);
+        assert_eq!(a.halted, b.halted);
+        assert_eq!(a.config.compare_mode, b.config.compare_mode);
+        assert_eq!(a.config.overflow_policy, b.config.overflow_policy);
+        assert_eq!(a.bytes_formatter.endianness(), b.bytes_formatter.endianness());
+
+        let mut registers = (a.registers.get_values(), b.registers.get_values());
+        registers.0.sort_by_key(|v| v.0.get_id());
+        registers.1.sort_by_key(|v| v.0.get_id());
+        assert_eq!(registers.0, registers.1);
+        assert_eq!(a.command_memory.entries(), b.command_memory.entries());
+        assert_eq!(a.data_memory.entries(), b.data_memory.entries());
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        for endianness in [Endianness::Little, Endianness::Big] {
+            let mut state = machine(endianness);
+            for _ in 0..3 {
+                assert_eq!(next_step(&mut state).unwrap(), StepOutcome::Executed);
+            }
+            state.flags = Flags::from_bits(0b1010);
+
+            let mut loaded = load_snapshot(&save_snapshot(&state)).unwrap();
+            assert_same_state(&state, &loaded);
+            assert_eq!(loaded.registers.get(RegisterName::Rg1).unwrap().as_u16(), 5);
+            assert_eq!(loaded.data_memory.get(3), if endianness == Endianness::Little { 5 } else { 0 });
+
+            // выполнение продолжается с сохранённого места
+            assert_eq!(next_step(&mut loaded).unwrap(), StepOutcome::Halted);
+            assert!(loaded.halted);
+            assert_same_state(&loaded, &load_snapshot(&save_snapshot(&loaded)).unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_snapshots() {
+        assert!(load_snapshot(b"VMMD\x01").is_err());
+        assert!(load_snapshot(b"VMSS\x01\x00").is_err());
+        assert!(load_snapshot(b"VMSS\x03").is_err());
+        assert!(load_snapshot(&save_snapshot(&machine(Endianness::Big))[..20]).is_err());
+    }
+}
\ No newline at end of file
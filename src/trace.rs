@@ -0,0 +1,183 @@
+use crate::command::Command;
+use crate::encoder::asm_encoder::encode_asm;
+use crate::flags::Flags;
+use crate::memory::data_memory::MemoryWrite;
+use crate::operand::Operand;
+use crate::register::RegisterName;
+use crate::vm::{next_step, StepOutcome, VmState};
+use crate::vm_fault::VmFault;
+use std::io;
+use std::io::Write;
+use strum_macros::EnumString;
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum TraceFormat {
+    #[default]
+    Text,
+    Jsonl
+}
+
+pub struct RegisterChange {
+    pub name: RegisterName,
+    pub old: u16,
+    pub new: u16
+}
+
+pub struct TraceEntry {
+    pub step: u64,
+    pub pc: u16,
+    pub command: Option<Command>,
+    pub value: Option<u16>,
+    pub registers: Vec<RegisterChange>,
+    pub flags: Flags,
+    pub memory: Vec<MemoryWrite>,
+    pub outcome: Result<StepOutcome, VmFault>
+}
+
+pub struct Tracer {
+    writer: Box<dyn Write>,
+    format: TraceFormat,
+    steps: u64,
+    error: Option<io::Error>
+}
+
+impl Tracer {
+
+    pub fn new(writer: Box<dyn Write>, format: TraceFormat) -> Tracer {
+        Tracer {
+            writer,
+            format,
+            steps: 0,
+            error: None
+        }
+    }
+
+    pub fn step(&mut self, state: &mut VmState) -> Result<StepOutcome, VmFault> {
+        self.steps += 1;
+        let entry = traced_step(state, self.steps);
+
+        let line = match self.format {
+            TraceFormat::Text => entry.to_text(),
+            TraceFormat::Jsonl => entry.to_json()
+        };
+        if self.error.is_none() {
+            self.error = writeln!(self.writer, "{line}").err();
+        }
+
+        entry.outcome
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.writer.flush()
+    }
+}
+
+pub fn traced_step(state: &mut VmState, step: u64) -> TraceEntry {
+    let pc = state.pc.pc;
+    let command = state.command_memory.get(pc).ok().flatten();
+    let value = command.map(|command| match command.operand {
+        Operand::Literal(v) => v,
+        Operand::SignedLiteral(v) => v as i16 as u16,
+        Operand::Register(name) => state.registers.get(name).unwrap().as_u16()
+    });
+    let registers_before = state.registers.get_values();
+
+    state.data_memory.record_writes();
+    state.data_memory.take_writes();
+    let outcome = next_step(state);
+
+    let registers = registers_before.into_iter()
+        .zip(state.registers.get_values())
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((name, old), (_, new))| RegisterChange { name, old, new })
+        .collect();
+
+    TraceEntry {
+        step,
+        pc,
+        command,
+        value,
+        registers,
+        flags: state.flags,
+        memory: state.data_memory.take_writes(),
+        outcome
+    }
+}
+
+impl TraceEntry {
+
+    pub fn to_text(&self) -> String {
+        let command = self.command.map(|v| encode_asm(&v)).unwrap_or_default();
+        let mut line = format!("{:>6} {:04X}: {:<12}", self.step, self.pc, command);
+
+        if let Some(value) = self.value {
+            line += &format!(" value={value:04X}");
+        }
+        for change in &self.registers {
+            let name: &str = change.name.into();
+            line += &format!(" {}:{:04X}->{:04X}", name.to_lowercase(), change.old, change.new);
+        }
+        for write in &self.memory {
+            line += &format!(" [{:04X}]:{:02X}->{:02X}", write.address, write.old, write.new);
+        }
+        line += &format!(" flags={}", self.flags);
+
+        match &self.outcome {
+            Ok(StepOutcome::Executed) => {}
+            Ok(StepOutcome::Halted) => line += " halted",
+            Ok(StepOutcome::RanOffEnd) => line += " ran-off-end",
+            Err(fault) => line += &format!(" {fault}")
+        }
+        line
+    }
+
+    pub fn to_json(&self) -> String {
+        let command = self.command.map(|v| json_string(&encode_asm(&v))).unwrap_or("null".to_string());
+        let value = self.value.map(|v| v.to_string()).unwrap_or("null".to_string());
+
+        let registers = self.registers.iter()
+            .map(|change| {
+                let name: &str = change.name.into();
+                format!("{}:[{},{}]", json_string(&name.to_lowercase()), change.old, change.new)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let memory = self.memory.iter()
+            .map(|write| format!("{{\"address\":{},\"old\":{},\"new\":{}}}", write.address, write.old, write.new))
+            .collect::<Vec<_>>()
+            .join(",");
+        let outcome = match &self.outcome {
+            Ok(StepOutcome::Executed) => json_string("executed"),
+            Ok(StepOutcome::Halted) => json_string("halted"),
+            Ok(StepOutcome::RanOffEnd) => json_string("ran_off_end"),
+            Err(fault) => format!("{},\"fault\":{}", json_string("fault"), json_string(&fault.kind.to_string()))
+        };
+
+        format!(
+            "{{\"step\":{},\"pc\":{},\"command\":{},\"value\":{},\"registers\":{{{}}},\"memory\":[{}],\"flags\":{},\"outcome\":{}}}",
+            self.step, self.pc, command, value, registers, memory, json_string(&self.flags.to_string()), outcome
+        )
+    }
+}
+
+// строка JSON в кавычках с экранированием спецсимволов
+fn json_string(text: &str) -> String {
+    let mut res = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => res += "\\\"",
+            '\\' => res += "\\\\",
+            '\n' => res += "\\n",
+            '\r' => res += "\\r",
+            '\t' => res += "\\t",
+            c if (c as u32) < 0x20 => res += &format!("\\u{:04x}", c as u32),
+            c => res.push(c)
+        }
+    }
+    res.push('"');
+    res
+}
\ No newline at end of file
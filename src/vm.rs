@@ -1,26 +1,84 @@
 use std::rc::Rc;
+use strum_macros::{EnumString, FromRepr};
 use crate::byte_formatter::byte_formatter::ByteFormatter;
 use crate::byte_formatter::little_endian_formatter::LittleEndianFormatter;
 use crate::command::CommandType;
 use crate::common::default_error::DefaultError;
 use crate::decoder::asm_decoder::parse_asm;
 use crate::file_loaders::load_string_file;
+use crate::flags::Flags;
 use crate::memory::command_memory::CommandMemory;
 use crate::memory::data_memory::DataMemory;
 use crate::memory::register_memory::RegisterMemory;
 use crate::operand::Operand;
 use crate::program_counter::ProgramCounter;
 use crate::register::RegisterName;
+use crate::vm_fault::{ArithmeticTrap, VmFault, VmFaultKind};
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, EnumString, FromRepr)]
+#[strum(ascii_case_insensitive)]
+#[repr(u8)]
+pub enum CompareMode {
+    // CMP выставляет только флаги, JMPG/JMPNG проверяют флаги (беззнаковое "больше")
+    #[default]
+    Flags,
+    // прежнее поведение: CMP записывает в ACC результат сравнения, JMPG/JMPNG проверяют ACC == 1
+    Legacy
+}
+
+// Арифметика ACC: 16-битные знаковые числа в дополнительном коде. Переполнением считается выход
+// результата ADD, SUB, MULT и DIV (-32768 / -1) за пределы i16, оно обрабатывается согласно политике.
+// Беззнаковый перенос/заём не является ошибкой и отражается только флагом C. MOD, DIVU, MODU,
+// логические операции и сдвиги переполниться не могут. Адреса памяти данных вычисляются по модулю 2^16,
+// выход PC за 0xFFFF всегда является ошибкой.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, EnumString, FromRepr)]
+#[strum(ascii_case_insensitive)]
+#[repr(u8)]
+pub enum OverflowPolicy {
+    // результат по модулю 2^16
+    #[default]
+    Wrap,
+    // результат ограничивается значениями -32768..=32767
+    Saturate,
+    // выполнение останавливается с ошибкой
+    Trap
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct VmConfig {
+    pub compare_mode: CompareMode,
+    pub overflow_policy: OverflowPolicy
+}
+
+// параметры, заданные в командной строке, заменяют сохранённые в снимке
+#[derive(Default, Copy, Clone)]
+pub struct VmConfigOverrides {
+    pub compare_mode: Option<CompareMode>,
+    pub overflow_policy: Option<OverflowPolicy>
+}
+
+impl VmConfigOverrides {
+
+    pub fn apply(&self, base: VmConfig) -> VmConfig {
+        VmConfig {
+            compare_mode: self.compare_mode.unwrap_or(base.compare_mode),
+            overflow_policy: self.overflow_policy.unwrap_or(base.overflow_policy)
+        }
+    }
+}
 
 pub struct VmState {
     pub command_memory: CommandMemory,
     pub data_memory: DataMemory,
     pub registers: RegisterMemory,
     pub pc: ProgramCounter,
+    pub flags: Flags,
+    pub halted: bool,
+    pub config: VmConfig,
     pub bytes_formatter: Rc<dyn ByteFormatter>
 }
 
-pub fn init_vm(bytes_formatter: Rc<dyn ByteFormatter>, command_memory: CommandMemory, data_memory: Option<DataMemory>) -> Result<VmState, String> {
+pub fn init_vm(bytes_formatter: Rc<dyn ByteFormatter>, command_memory: CommandMemory, data_memory: Option<DataMemory>, config: VmConfig) -> Result<VmState, String> {
 
     // let file = parse_bin(&load_file("bin").default_res()?, bytes_formatter.clone().deref())?;
     // let commands = parse_asm(&load_string_file(data_path).default_res()?)?;
@@ -36,30 +94,55 @@ pub fn init_vm(bytes_formatter: Rc<dyn ByteFormatter>, command_memory: CommandMe
         data_memory: data_memory.unwrap_or(DataMemory::default()),
         registers,
         pc,
+        flags: Flags::default(),
+        halted: false,
+        config,
         bytes_formatter,
     })
 }
 
-pub fn next_step(state: &mut VmState) -> Result<bool, String> {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StepOutcome {
+    Executed,
+    Halted,
+    RanOffEnd
+}
+
+pub fn next_step(state: &mut VmState) -> Result<StepOutcome, VmFault> {
+    let pc = state.pc.pc;
+    let word = state.command_memory.get_word(pc);
+
+    execute_step(state).map_err(|kind| VmFault { pc, word, kind })
+}
+
+fn execute_step(state: &mut VmState) -> Result<StepOutcome, VmFaultKind> {
+    if state.halted {
+        return Err(VmFaultKind::Halted);
+    }
+
     let command_memory = &state.command_memory;
     let data_memory = &mut state.data_memory;
     let registers = &mut state.registers;
     let bytes_formatter = &mut state.bytes_formatter;
+    let flags = &mut state.flags;
+    let config = &state.config;
 
     let pc = &mut state.pc;
 
     pc.reset_flag();
-    let command_o = command_memory.get(pc.pc).unwrap_or(None);
+    let command_o = command_memory.get(pc.pc)?;
     let command = if let Some(v) = command_o {
         v
     } else {
-        return Ok(false);
+        state.halted = true;
+        return Ok(StepOutcome::RanOffEnd);
     };
 
 
     let value = match command.operand {
         Operand::Literal(v) => {v}
-        Operand::Register(register_id) => registers.get_mut(register_id).expect("invalid register ref").as_u16()
+        Operand::SignedLiteral(v) => v as i16 as u16,
+        Operand::Register(register_id) => registers.get(register_id).ok_or(VmFaultKind::InvalidRegister(register_id.get_id()))?.as_u16()
     };
 
 
@@ -70,26 +153,35 @@ pub fn next_step(state: &mut VmState) -> Result<bool, String> {
         }
         CommandType::ADD => {
             let acc = registers.get_mut(RegisterName::Acc).unwrap();
-            acc.put_u16(acc.as_u16().cast_signed().wrapping_add(value.cast_signed()).cast_unsigned());
+            let (a, b) = (acc.as_u16().cast_signed(), value.cast_signed());
+            let (wrapped, overflow) = a.overflowing_add(b);
+            let res = apply_overflow_policy(config.overflow_policy, wrapped, overflow, a.saturating_add(b))?;
+            *flags = Flags::from_result(res, acc.as_u16().overflowing_add(value).1, overflow);
+            acc.put_u16(res);
         }
         CommandType::LAC => {
             let acc = registers.get_mut(RegisterName::Acc).unwrap();
-            let bytes = [data_memory.get(value), data_memory.get(value + 1)];
-            acc.put_u16(bytes_formatter.wrap_bytes(bytes))
+            acc.put_u16(read_word(data_memory, bytes_formatter.as_ref(), value))
         }
         CommandType::DAC => {
             let acc = registers.get_mut(RegisterName::Acc).unwrap();
-            let bytes = bytes_formatter.unwrap_bytes(acc.as_u16());
-            data_memory.put_bytes(value, &bytes);
+            write_word(data_memory, bytes_formatter.as_ref(), value, acc.as_u16());
         }
         CommandType::SUB => {
             let acc = registers.get_mut(RegisterName::Acc).unwrap();
-            acc.put_u16(acc.as_u16() - value);
+            let (a, b) = (acc.as_u16().cast_signed(), value.cast_signed());
+            let (wrapped, overflow) = a.overflowing_sub(b);
+            let res = apply_overflow_policy(config.overflow_policy, wrapped, overflow, a.saturating_sub(b))?;
+            *flags = Flags::from_result(res, acc.as_u16() < value, overflow);
+            acc.put_u16(res);
         }
         CommandType::CMP => {
             let acc = registers.get_mut(RegisterName::Acc).unwrap();
-            let res = acc.as_u16().cmp(&value) as i16;
-            acc.put_u16(res.cast_unsigned());
+            *flags = sub_flags(acc.as_u16(), value);
+            if config.compare_mode == CompareMode::Legacy {
+                let res = acc.as_u16().cmp(&value) as i16;
+                acc.put_u16(res.cast_unsigned());
+            }
         }
         CommandType::MOV => {
             let acc = registers.get_mut(RegisterName::Acc).unwrap();;
@@ -98,31 +190,492 @@ pub fn next_step(state: &mut VmState) -> Result<bool, String> {
         CommandType::LRG => {
             let acc_value = registers.get_mut(RegisterName::Acc).unwrap().as_u16();
             match command.operand {
-                Operand::Literal(_) => { return Err("invalid lrg command".to_string()); }
+                Operand::Literal(_) | Operand::SignedLiteral(_) => { return Err(VmFaultKind::IllegalOperand(command.command_type)); }
                 Operand::Register(target) => {
-                    registers.get_mut(target).expect("invalid register").put_u16(acc_value);
+                    registers.get_mut(target).ok_or(VmFaultKind::InvalidRegister(target.get_id()))?.put_u16(acc_value);
                 }
             }
         }
         CommandType::JMPG => {
-            let acc_value = registers.get_mut(RegisterName::Acc).unwrap().as_u16();
-            if acc_value == 1 {
+            if is_greater(registers, flags, config) {
                 pc.set(value);
             }
         }
         CommandType::JMPNG => {
-            let acc_value = registers.get_mut(RegisterName::Acc).unwrap().as_u16();
-            if acc_value != 1 {
+            if !is_greater(registers, flags, config) {
                 pc.set(value);
             }
         }
         CommandType::MULT => {
             let acc = registers.get_mut(RegisterName::Acc).unwrap();
-            acc.put_u16(acc.as_u16().cast_signed().wrapping_mul(value.cast_signed()).cast_unsigned());
+            let (a, b) = (acc.as_u16().cast_signed(), value.cast_signed());
+            let (wrapped, overflow) = a.overflowing_mul(b);
+            let res = apply_overflow_policy(config.overflow_policy, wrapped, overflow, a.saturating_mul(b))?;
+            *flags = Flags::from_result(res, acc.as_u16().checked_mul(value).is_none(), overflow);
+            acc.put_u16(res);
+        }
+        CommandType::MOVH => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16((value << 8) | (acc.as_u16() & 0xff));
+        }
+        CommandType::PUSH => {
+            push(registers, data_memory, bytes_formatter.as_ref(), value)?;
+        }
+        CommandType::POP => {
+            match command.operand {
+                Operand::Literal(_) | Operand::SignedLiteral(_) => { return Err(VmFaultKind::IllegalOperand(command.command_type)); }
+                Operand::Register(target) => {
+                    let value = pop(registers, data_memory, bytes_formatter.as_ref())?;
+                    registers.get_mut(target).ok_or(VmFaultKind::InvalidRegister(target.get_id()))?.put_u16(value);
+                }
+            }
+        }
+        CommandType::CALL => {
+            push(registers, data_memory, bytes_formatter.as_ref(), next_address(pc.pc)?)?;
+            pc.set(value);
+        }
+        CommandType::RET => {
+            let address = pop(registers, data_memory, bytes_formatter.as_ref())?;
+            pc.set(address);
+        }
+        CommandType::AND => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16(acc.as_u16() & value);
+        }
+        CommandType::OR => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16(acc.as_u16() | value);
+        }
+        CommandType::XOR => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16(acc.as_u16() ^ value);
+        }
+        CommandType::NOT => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16(!acc.as_u16());
+        }
+        CommandType::SHL => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16(acc.as_u16().checked_shl(value as u32).unwrap_or(0));
+        }
+        CommandType::SHR => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16(acc.as_u16().checked_shr(value as u32).unwrap_or(0));
+        }
+        CommandType::ROL => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16(acc.as_u16().rotate_left(value as u32));
+        }
+        CommandType::ROR => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            acc.put_u16(acc.as_u16().rotate_right(value as u32));
+        }
+        CommandType::DIV => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            check_divisor(value)?;
+            let (a, b) = (acc.as_u16().cast_signed(), value.cast_signed());
+            let (wrapped, overflow) = a.overflowing_div(b);
+            acc.put_u16(apply_overflow_policy(config.overflow_policy, wrapped, overflow, a.saturating_div(b))?);
+        }
+        CommandType::MOD => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            check_divisor(value)?;
+            acc.put_u16(acc.as_u16().cast_signed().wrapping_rem(value.cast_signed()).cast_unsigned());
+        }
+        CommandType::DIVU => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            check_divisor(value)?;
+            acc.put_u16(acc.as_u16() / value);
+        }
+        CommandType::MODU => {
+            let acc = registers.get_mut(RegisterName::Acc).unwrap();
+            check_divisor(value)?;
+            acc.put_u16(acc.as_u16() % value);
+        }
+        CommandType::JZ => {
+            if flags.zero {
+                pc.set(value);
+            }
+        }
+        CommandType::JNZ => {
+            if !flags.zero {
+                pc.set(value);
+            }
+        }
+        CommandType::JL => {
+            if flags.negative != flags.overflow {
+                pc.set(value);
+            }
+        }
+        CommandType::JLE => {
+            if flags.zero || flags.negative != flags.overflow {
+                pc.set(value);
+            }
+        }
+        CommandType::JGE => {
+            if flags.negative == flags.overflow {
+                pc.set(value);
+            }
+        }
+        CommandType::JC => {
+            if flags.carry {
+                pc.set(value);
+            }
+        }
+        CommandType::JO => {
+            if flags.overflow {
+                pc.set(value);
+            }
+        }
+        CommandType::HALT => {
+            state.halted = true;
+            return Ok(StepOutcome::Halted);
         }
     }
     if !pc.changed {
-        pc.set(pc.pc + 2)
+        pc.set(next_address(pc.pc)?)
     }
-    Ok(true)
-}
\ No newline at end of file
+    Ok(StepOutcome::Executed)
+}
+
+fn apply_overflow_policy(policy: OverflowPolicy, wrapped: i16, overflow: bool, saturated: i16) -> Result<u16, VmFaultKind> {
+    if !overflow {
+        return Ok(wrapped.cast_unsigned());
+    }
+    match policy {
+        OverflowPolicy::Wrap => Ok(wrapped.cast_unsigned()),
+        OverflowPolicy::Saturate => Ok(saturated.cast_unsigned()),
+        OverflowPolicy::Trap => Err(VmFaultKind::ArithmeticTrap(ArithmeticTrap::Overflow))
+    }
+}
+
+fn next_address(pc: u16) -> Result<u16, VmFaultKind> {
+    pc.checked_add(2).ok_or(VmFaultKind::PcOutOfRange)
+}
+
+// флаги вычитания acc - value: C — заём (беззнаковое acc < value), V — знаковое переполнение
+fn sub_flags(acc: u16, value: u16) -> Flags {
+    let (res, carry) = acc.overflowing_sub(value);
+    let overflow = acc.cast_signed().overflowing_sub(value.cast_signed()).1;
+    Flags::from_result(res, carry, overflow)
+}
+
+fn is_greater(registers: &RegisterMemory, flags: &Flags, config: &VmConfig) -> bool {
+    match config.compare_mode {
+        CompareMode::Flags => !flags.carry && !flags.zero,
+        CompareMode::Legacy => registers.get(RegisterName::Acc).unwrap().as_u16() == 1
+    }
+}
+
+fn check_divisor(value: u16) -> Result<(), VmFaultKind> {
+    if value == 0 {
+        return Err(VmFaultKind::ArithmeticTrap(ArithmeticTrap::DivisionByZero));
+    }
+    Ok(())
+}
+
+// стек растёт вниз от конца памяти данных: SP = 0 означает пустой стек,
+// ниже STACK_LIMIT стек расти не может
+pub const STACK_LIMIT: u16 = 0xff00;
+
+fn read_word(data_memory: &DataMemory, bytes_formatter: &dyn ByteFormatter, address: u16) -> u16 {
+    bytes_formatter.wrap_bytes([data_memory.get(address), data_memory.get(address.wrapping_add(1))])
+}
+
+fn write_word(data_memory: &mut DataMemory, bytes_formatter: &dyn ByteFormatter, address: u16, value: u16) {
+    data_memory.put_bytes(address, &bytes_formatter.unwrap_bytes(value));
+}
+
+fn push(registers: &mut RegisterMemory, data_memory: &mut DataMemory, bytes_formatter: &dyn ByteFormatter, value: u16) -> Result<(), VmFaultKind> {
+    let sp = registers.get_mut(RegisterName::Sp).unwrap();
+    let new_sp = sp.as_u16().wrapping_sub(2);
+    if new_sp < STACK_LIMIT {
+        return Err(VmFaultKind::StackOverflow);
+    }
+
+    sp.put_u16(new_sp);
+    write_word(data_memory, bytes_formatter, new_sp, value);
+    Ok(())
+}
+
+fn pop(registers: &mut RegisterMemory, data_memory: &DataMemory, bytes_formatter: &dyn ByteFormatter) -> Result<u16, VmFaultKind> {
+    let sp = registers.get_mut(RegisterName::Sp).unwrap();
+    if sp.as_u16() == 0 {
+        return Err(VmFaultKind::StackUnderflow);
+    }
+    if sp.as_u16() < STACK_LIMIT {
+        return Err(VmFaultKind::InvalidStackPointer(sp.as_u16()));
+    }
+
+    let value = read_word(data_memory, bytes_formatter, sp.as_u16());
+    sp.put_u16(sp.as_u16().wrapping_add(2));
+    Ok(value)
+}
+
+pub fn stack_contents(state: &VmState) -> Vec<u16> {
+    let sp = state.registers.get(RegisterName::Sp).unwrap().as_u16();
+    if sp < STACK_LIMIT {
+        return Vec::new();
+    }
+
+    (sp..=u16::MAX).step_by(2)
+        .map(|address| read_word(&state.data_memory, state.bytes_formatter.as_ref(), address))
+        .collect()
+}
+
+pub enum RunStop {
+    Halted,
+    RanOffEnd,
+    StepBudget,
+    Fault(VmFault)
+}
+
+pub struct RunResult {
+    pub steps: u64,
+    pub stop: RunStop
+}
+
+pub fn run_to_end(state: &mut VmState, max_steps: u64, mut step: impl FnMut(&mut VmState) -> Result<StepOutcome, VmFault>) -> RunResult {
+    // снимок мог быть сохранён уже после halt
+    if state.halted {
+        return RunResult { steps: 0, stop: RunStop::Halted };
+    }
+
+    let mut steps = 0;
+    while steps < max_steps {
+        match step(state) {
+            Ok(StepOutcome::Executed) => steps += 1,
+            Ok(StepOutcome::Halted) => return RunResult { steps: steps + 1, stop: RunStop::Halted },
+            Ok(StepOutcome::RanOffEnd) => return RunResult { steps, stop: RunStop::RanOffEnd },
+            Err(e) => return RunResult { steps, stop: RunStop::Fault(e) }
+        }
+    }
+
+    RunResult { steps, stop: RunStop::StepBudget }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::Command;
+    use crate::encoder::bin_encoder::encode_command;
+
+    fn load(program: &[Command], config: VmConfig) -> VmState {
+        let bytes_formatter: Rc<dyn ByteFormatter> = Rc::new(LittleEndianFormatter {});
+        let code = program.iter().flat_map(|command| encode_command(command, bytes_formatter.clone())).collect();
+        let command_memory = CommandMemory::load(code, 0, bytes_formatter.clone());
+        init_vm(bytes_formatter, command_memory, None, config).unwrap()
+    }
+
+    // загружает программу и задаёт начальные значения ACC и RG1
+    fn load_with_operands(program: &[Command], config: VmConfig, acc: i16, operand: i16) -> VmState {
+        let mut state = load(program, config);
+        state.registers.get_mut(RegisterName::Acc).unwrap().put_u16(acc.cast_unsigned());
+        state.registers.get_mut(RegisterName::Rg1).unwrap().put_u16(operand.cast_unsigned());
+        state
+    }
+
+    fn with_rg1(command_type: CommandType) -> Command {
+        Command { command_type, operand: Operand::Register(RegisterName::Rg1) }
+    }
+
+    // выполняет `command rg1` при заданных ACC и RG1, возвращает новое значение ACC
+    fn execute(command_type: CommandType, policy: OverflowPolicy, acc: i16, operand: i16) -> Result<u16, VmFaultKind> {
+        let config = VmConfig { overflow_policy: policy, ..VmConfig::default() };
+        let mut state = load_with_operands(&[with_rg1(command_type)], config, acc, operand);
+
+        next_step(&mut state).map_err(|fault| fault.kind)?;
+        Ok(state.registers.get(RegisterName::Acc).unwrap().as_u16())
+    }
+
+    // флаги после `command rg1` в виде "ZNCV"
+    fn flags_after(command_type: CommandType, acc: i16, operand: i16) -> String {
+        let mut state = load_with_operands(&[with_rg1(command_type)], VmConfig::default(), acc, operand);
+        next_step(&mut state).unwrap();
+        state.flags.to_string()
+    }
+
+    // выполняет `cmp rg1` и `jump 100`, возвращает true, если переход выполнен
+    fn jumps_after_cmp(compare_mode: CompareMode, jump: CommandType, acc: i16, operand: i16) -> bool {
+        let program = [with_rg1(CommandType::CMP), Command { command_type: jump, operand: Operand::Literal(100) }];
+        let config = VmConfig { compare_mode, ..VmConfig::default() };
+        let mut state = load_with_operands(&program, config, acc, operand);
+
+        next_step(&mut state).unwrap();
+        next_step(&mut state).unwrap();
+        state.pc.pc == 100
+    }
+
+    fn is_overflow_trap(result: Result<u16, VmFaultKind>) -> bool {
+        matches!(result, Err(VmFaultKind::ArithmeticTrap(ArithmeticTrap::Overflow)))
+    }
+
+    #[test]
+    fn add_overflow_policies() {
+        assert_eq!(execute(CommandType::ADD, OverflowPolicy::Wrap, i16::MAX, 1).ok(), Some(0x8000));
+        assert_eq!(execute(CommandType::ADD, OverflowPolicy::Saturate, i16::MAX, 1).ok(), Some(0x7fff));
+        assert_eq!(execute(CommandType::ADD, OverflowPolicy::Saturate, i16::MIN, -1).ok(), Some(0x8000));
+        assert!(is_overflow_trap(execute(CommandType::ADD, OverflowPolicy::Trap, i16::MAX, 1)));
+        // беззнаковый перенос переполнением не считается
+        assert_eq!(execute(CommandType::ADD, OverflowPolicy::Trap, -1, 1).ok(), Some(0));
+    }
+
+    #[test]
+    fn sub_overflow_policies() {
+        assert_eq!(execute(CommandType::SUB, OverflowPolicy::Wrap, i16::MIN, 1).ok(), Some(0x7fff));
+        assert_eq!(execute(CommandType::SUB, OverflowPolicy::Saturate, i16::MIN, 1).ok(), Some(0x8000));
+        assert_eq!(execute(CommandType::SUB, OverflowPolicy::Saturate, i16::MAX, -1).ok(), Some(0x7fff));
+        assert!(is_overflow_trap(execute(CommandType::SUB, OverflowPolicy::Trap, i16::MIN, 1)));
+        assert_eq!(execute(CommandType::SUB, OverflowPolicy::Trap, 0, 1).ok(), Some(0xffff));
+    }
+
+    #[test]
+    fn mult_overflow_policies() {
+        assert_eq!(execute(CommandType::MULT, OverflowPolicy::Wrap, 0x4000, 2).ok(), Some(0x8000));
+        assert_eq!(execute(CommandType::MULT, OverflowPolicy::Saturate, 0x4000, 2).ok(), Some(0x7fff));
+        assert_eq!(execute(CommandType::MULT, OverflowPolicy::Saturate, -0x4000, 4).ok(), Some(0x8000));
+        assert!(is_overflow_trap(execute(CommandType::MULT, OverflowPolicy::Trap, 0x4000, 2)));
+        assert_eq!(execute(CommandType::MULT, OverflowPolicy::Trap, -300, 100).ok(), Some((-30000i16).cast_unsigned()));
+    }
+
+    #[test]
+    fn div_overflow_policies() {
+        assert_eq!(execute(CommandType::DIV, OverflowPolicy::Wrap, i16::MIN, -1).ok(), Some(0x8000));
+        assert_eq!(execute(CommandType::DIV, OverflowPolicy::Saturate, i16::MIN, -1).ok(), Some(0x7fff));
+        assert!(is_overflow_trap(execute(CommandType::DIV, OverflowPolicy::Trap, i16::MIN, -1)));
+        assert_eq!(execute(CommandType::DIV, OverflowPolicy::Trap, -7, 2).ok(), Some((-3i16).cast_unsigned()));
+
+        for policy in [OverflowPolicy::Wrap, OverflowPolicy::Saturate, OverflowPolicy::Trap] {
+            let result = execute(CommandType::DIV, policy, 1, 0);
+            assert!(matches!(result, Err(VmFaultKind::ArithmeticTrap(ArithmeticTrap::DivisionByZero))));
+        }
+    }
+
+    #[test]
+    fn halted_state_runs_zero_steps() {
+        let mut state = load(&[Command { command_type: CommandType::HALT, operand: Operand::Literal(0) }], VmConfig::default());
+        assert!(matches!(run_to_end(&mut state, 10, next_step).stop, RunStop::Halted));
+
+        let result = run_to_end(&mut state, 10, next_step);
+        assert_eq!(result.steps, 0);
+        assert!(matches!(result.stop, RunStop::Halted));
+    }
+
+    #[test]
+    fn sub_flags_boundaries() {
+        assert_eq!(sub_flags((-1i16).cast_unsigned(), 1).to_string(), "-N--");
+        assert_eq!(sub_flags(i16::MIN.cast_unsigned(), 1).to_string(), "---V");
+        assert_eq!(sub_flags(5, 5).to_string(), "Z---");
+        assert_eq!(sub_flags(1, (-1i16).cast_unsigned()).to_string(), "--C-");
+        assert_eq!(sub_flags(i16::MAX.cast_unsigned(), (-1i16).cast_unsigned()).to_string(), "-NCV");
+    }
+
+    #[test]
+    fn arithmetic_flags() {
+        // C — беззнаковый перенос, V — знаковое переполнение
+        assert_eq!(flags_after(CommandType::ADD, -1, 1), "Z-C-");
+        assert_eq!(flags_after(CommandType::ADD, i16::MAX, 1), "-N-V");
+        assert_eq!(flags_after(CommandType::ADD, -1, -1), "-NC-");
+        assert_eq!(flags_after(CommandType::ADD, i16::MIN, -1), "--CV");
+
+        // C — заём
+        assert_eq!(flags_after(CommandType::SUB, 1, 2), "-NC-");
+        assert_eq!(flags_after(CommandType::SUB, i16::MIN, 1), "---V");
+        assert_eq!(flags_after(CommandType::SUB, 5, 5), "Z---");
+        assert_eq!(flags_after(CommandType::SUB, 0, i16::MIN), "-NCV");
+
+        // C — беззнаковое произведение не помещается в 16 бит
+        assert_eq!(flags_after(CommandType::MULT, 0x100, 0x100), "Z-CV");
+        assert_eq!(flags_after(CommandType::MULT, -1, 1), "-N--");
+        assert_eq!(flags_after(CommandType::MULT, -1, -1), "--C-");
+        assert_eq!(flags_after(CommandType::MULT, 0x4000, 2), "-N-V");
+    }
+
+    #[test]
+    fn conditional_jumps_after_cmp() {
+        for compare_mode in [CompareMode::Flags, CompareMode::Legacy] {
+            for (a, b) in [(-1i16, 1i16), (i16::MIN, 1), (5, 5), (1, -1)] {
+                let above = a.cast_unsigned() > b.cast_unsigned();
+                let expected = [
+                    (CommandType::JZ, a == b),
+                    (CommandType::JNZ, a != b),
+                    (CommandType::JL, a < b),
+                    (CommandType::JLE, a <= b),
+                    (CommandType::JGE, a >= b),
+                    (CommandType::JC, a.cast_unsigned() < b.cast_unsigned()),
+                    (CommandType::JO, a.checked_sub(b).is_none()),
+                    (CommandType::JMPG, above),
+                    (CommandType::JMPNG, !above)
+                ];
+                for (jump, taken) in expected {
+                    assert_eq!(jumps_after_cmp(compare_mode, jump, a, b), taken, "{jump:?} after cmp {a}, {b} ({compare_mode:?})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jmpg_matches_legacy_compare() {
+        for (a, b) in [(-1i16, 1i16), (i16::MIN, 1), (5, 5), (1, -1), (0, 0), (3, 2), (2, 3)] {
+            for jump in [CommandType::JMPG, CommandType::JMPNG] {
+                assert_eq!(jumps_after_cmp(CompareMode::Flags, jump, a, b), jumps_after_cmp(CompareMode::Legacy, jump, a, b), "{jump:?} after cmp {a}, {b}");
+            }
+        }
+    }
+
+    fn literal(command_type: CommandType, value: u16) -> Command {
+        Command { command_type, operand: Operand::Literal(value) }
+    }
+
+    fn step_kind(state: &mut VmState) -> Result<StepOutcome, VmFaultKind> {
+        next_step(state).map_err(|fault| fault.kind)
+    }
+
+    #[test]
+    fn stack_overflows_at_limit() {
+        let mut state = load(&[literal(CommandType::PUSH, 1), literal(CommandType::JMP, 0)], VmConfig::default());
+        let result = run_to_end(&mut state, 1000, next_step);
+        assert!(matches!(result.stop, RunStop::Fault(VmFault { kind: VmFaultKind::StackOverflow, .. })));
+        assert_eq!(state.registers.get(RegisterName::Sp).unwrap().as_u16(), STACK_LIMIT);
+        assert_eq!(stack_contents(&state).len(), 0x80);
+    }
+
+    #[test]
+    fn pop_from_empty_stack_underflows() {
+        let mut state = load(&[with_rg1(CommandType::POP)], VmConfig::default());
+        assert!(matches!(step_kind(&mut state), Err(VmFaultKind::StackUnderflow)));
+    }
+
+    #[test]
+    fn stack_pointer_outside_of_stack() {
+        let program = [
+            literal(CommandType::MOV, 0x12),
+            Command { command_type: CommandType::LRG, operand: Operand::Register(RegisterName::Sp) },
+            with_rg1(CommandType::POP)
+        ];
+        let mut state = load(&program, VmConfig::default());
+        step_kind(&mut state).unwrap();
+        step_kind(&mut state).unwrap();
+        assert!(matches!(step_kind(&mut state), Err(VmFaultKind::InvalidStackPointer(0x12))));
+    }
+
+    #[test]
+    fn call_pushes_return_address() {
+        let program = [
+            literal(CommandType::NOP, 0),
+            literal(CommandType::CALL, 6),
+            literal(CommandType::HALT, 0),
+            literal(CommandType::RET, 0)
+        ];
+        let mut state = load(&program, VmConfig::default());
+        step_kind(&mut state).unwrap();
+        step_kind(&mut state).unwrap();
+        assert_eq!(state.pc.pc, 6);
+        assert_eq!(stack_contents(&state), vec![4]);
+
+        step_kind(&mut state).unwrap();
+        assert_eq!(state.pc.pc, 4);
+        assert_eq!(state.registers.get(RegisterName::Sp).unwrap().as_u16(), 0);
+        assert!(matches!(step_kind(&mut state), Ok(StepOutcome::Halted)));
+    }
+}
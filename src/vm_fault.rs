@@ -0,0 +1,73 @@
+use crate::command::CommandType;
+use crate::decoder::bin_decoder::DecodeError;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Copy, Clone)]
+pub enum ArithmeticTrap {
+    DivisionByZero,
+    Overflow
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum VmFaultKind {
+    InvalidOpcode(u16),
+    InvalidOperandType(u16),
+    InvalidRegister(u16),
+    InvalidSignedLiteral(u16),
+    IllegalOperand(CommandType),
+    ArithmeticTrap(ArithmeticTrap),
+    StackOverflow,
+    StackUnderflow,
+    InvalidStackPointer(u16),
+    PcOutOfRange,
+    Halted
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct VmFault {
+    pub pc: u16,
+    pub word: Option<u16>,
+    pub kind: VmFaultKind
+}
+
+impl From<DecodeError> for VmFaultKind {
+    fn from(value: DecodeError) -> Self {
+        match value {
+            DecodeError::Command(id) => VmFaultKind::InvalidOpcode(id),
+            DecodeError::OperandType(id) => VmFaultKind::InvalidOperandType(id),
+            DecodeError::Register(id) => VmFaultKind::InvalidRegister(id),
+            DecodeError::SignedLiteral(value) => VmFaultKind::InvalidSignedLiteral(value)
+        }
+    }
+}
+
+impl Display for VmFaultKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmFaultKind::InvalidOpcode(id) => write!(f, "invalid opcode {id}"),
+            VmFaultKind::InvalidOperandType(id) => write!(f, "invalid operand type {id}"),
+            VmFaultKind::InvalidRegister(id) => write!(f, "invalid register {id}"),
+            VmFaultKind::InvalidSignedLiteral(value) => write!(f, "non-negative signed literal {value}"),
+            VmFaultKind::IllegalOperand(command_type) => {
+                let command_name: &str = command_type.into();
+                write!(f, "illegal operand kind for {command_name}")
+            }
+            VmFaultKind::ArithmeticTrap(ArithmeticTrap::DivisionByZero) => write!(f, "division by zero"),
+            VmFaultKind::ArithmeticTrap(ArithmeticTrap::Overflow) => write!(f, "arithmetic overflow"),
+            VmFaultKind::StackOverflow => write!(f, "stack overflow"),
+            VmFaultKind::StackUnderflow => write!(f, "stack underflow"),
+            VmFaultKind::InvalidStackPointer(sp) => write!(f, "stack pointer {sp:04X} is outside of the stack"),
+            VmFaultKind::PcOutOfRange => write!(f, "program counter out of range"),
+            VmFaultKind::Halted => write!(f, "machine is halted")
+        }
+    }
+}
+
+impl Display for VmFault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.word {
+            Some(word) => write!(f, "fault at {:04X} (word {:04X}): {}", self.pc, word, self.kind),
+            None => write!(f, "fault at {:04X}: {}", self.pc, self.kind)
+        }
+    }
+}
\ No newline at end of file